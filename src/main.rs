@@ -17,9 +17,10 @@ use std::*;
 extern crate scopeguard;
 
 //note bookmark is not stored in sqlite, but in separate file. Because bookmark might be written quite frequently. (E.g. maybe each time user navigates)
+//note bookmark file also holds file size, mtime and a short hash of bytes around bookmark. If the file has been edited, bookmark is relocated by searching nearby for those bytes
 //note recent file list is stored in sqlite so it can support huge number of files sorted by last open time.
 
-//note correctness is sacrificed for better performance (not checking grapheme clusters)
+//note correctness is sacrificed for better performance (not checking grapheme clusters) unless `se grapheme` is used
 //note it does not read text files as grapheme clusters, in other words, sometimes a group of chars are displayed and it might not end at boundary of grapheme cluster
 
 //note there are 2 ways of displaying file content: PAGE-LIKE vs SLIDING-WINDOW
@@ -41,6 +42,14 @@ const _: () = assert!(
     DEF_DISPLAY_HEIGHT >= MIN_DISPLAY_HEIGHT,
     "Constraint on const"
 );
+const MIN_TEXT_WIDTH: usize = 8; //note when gutter takes too much of display line
+const TERM_RESERVED_ROWS: usize = 2; //note for prompt and END-OF-FILE when display height is taken from terminal size (plus one more row for status line if enabled)
+
+//FULL-SCREEN
+//`tui` enters full-screen mode (alternate screen buffer, raw mode) where keys act immediately without Enter
+//PgDn/space/f/Enter for next page, PgUp/b for prev page, Down/j and Up/k for moving one display line, Home/g and End/G for top and bottom
+//`:` for running any line-oriented command, `/` and `?` for searching forward and backward, q for going back to line-oriented mode
+//note output of a command is shown in status row (or on its own screen until a key is pressed if it takes more than one line), `ol` is not available
 
 //SLIDING-WINDOW
 //w move up one line
@@ -52,12 +61,45 @@ const _: () = assert!(
 //:{number} for jumping to line number
 //{number}% for jumping to %
 //slash (/) for searching
+//question mark (?) for searching backward
+//&{pattern} for showing only lines containing the pattern (a long line is shown as a window around the match), & alone for showing all lines again
+//jm for jumping between matching brackets of minified JSON, jn for jumping to next sibling key (or element), ju for jumping to opening bracket of parent container (a JSON document is assumed to start at start of line)
+//pp for pretty-printing (re-indenting) JSON or XML (told by the first char of line) from the caret, up to pplimit bytes, pp json/pp xml for choosing the format explicitly
+//t {timestamp} (e.g. t 2026-10-17T13:05) for jumping to the first record at or after the time in a time-ordered log (binary search by byte offset, timezone is ignored)
+//bs {key} for jumping to the first line (whose key starts with the given key) in a sorted file (binary search by byte offset, lines compared bytewise)
+//c {number} for jumping to column of the current record in CSV mode
+//count /{pattern} for counting (non-overlapping) matches in the whole file and lines containing them
+//Ctrl-C for cancelling a long search (progress is printed periodically)
 //{number} for jumping to a certain search result
 //se/set for setting options, e.g. se regex/se regex!/se noregex for toggling searching mode, se windowsize {number} for WINDOW size
+//se hex/se mixed/se text for switching view mode (hex dump, text with invalid bytes shown as \xNN escapes, plain text)
+//se ctrl=caret/se ctrl=hex/se ctrl=glyph/se ctrl=raw for choosing how control chars and invalid bytes are displayed
+//se ambiwidth=single/se ambiwidth=double for width of East Asian Ambiguous chars
+//se nu/se nonu for toggling line numbers (? if line number is unknown because it is too far from known position)
+//se offsets/se offsets=hex/se nooffsets for toggling byte offsets (decimal or hex) of the first char of each display line
+//se ws/se nows for toggling whether search wraps around the end of file
+//se ic/se noic, se scs/se noscs for case-insensitive search (smartcase: case-sensitive if pattern contains uppercase char)
+//se wholeword/se nowholeword for matching whole words only
+//se jsonpp/se nojsonpp for toggling showing re-indented JSON (instead of the page) after jm/jn/ju
+//se loghl/se nologhl for toggling coloring of log levels, timestamps, IPs, UUIDs and quoted strings by rules from file logrules in app support dir (written with defaults if missing)
+//se hl/se nohl for toggling highlighting of matches of last search pattern (off by default when stdout is not a terminal)
+//se status/se nostatus for toggling status line after each page
+//se csv/se csv={char}/se csv=tab/se tsv/se nocsv for toggling CSV mode (one field per display line with column number and header name taken from the first record, a record is assumed to start at start of line)
+//se bsfield={number}/se bsdelim={char}/se bsdelim=tab for comparing a delimited field (1-based, 0 for whole line, tab by default) by bs
+//se pplimit={number} for bytes taken by pp
+//se ts={number} for tab stop
+//se grapheme/se nographeme for toggling grapheme-cluster-aware rendering (slower)
 //e for reloading the file
 //e ++enc=<encoding> for reloading the file with encoding
 //v for selecting mode (and then use w/s/a/d to move around and press y to copy to clipboard (calling xclip or customized command). Or just press enter with empty input for printing on stdout. Or just input `tee` for writing to a file.)
 
+const DEF_TABSTOP: usize = 8;
+const MIN_TABSTOP: usize = 1;
+const MAX_TABSTOP: usize = 16; //note must stay below MIN_DISPLAY_LINE_WIDTH
+const _: () = assert!(DEF_TABSTOP >= MIN_TABSTOP, "Constraint on const");
+const _: () = assert!(DEF_TABSTOP <= MAX_TABSTOP, "Constraint on const");
+const _: () = assert!(MAX_TABSTOP < MIN_DISPLAY_LINE_WIDTH, "Constraint on const");
+
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const _: () = assert!(!PKG_NAME.is_empty(), "Constraint on const");
 
@@ -68,6 +110,43 @@ const MIN_CACHE_SIZE: usize = 3;
 const _: () = assert!(DEF_WIND_SIZE >= MIN_WIND_SIZE, "Constraint on const");
 const _: () = assert!(DEF_CACHE_SIZE >= MIN_CACHE_SIZE, "Constraint on const");
 
+const LINE_COUNT_SCAN_LIMIT: u64 = 0x4000000; //note line number is shown as ? if counting newlines needs reading more than this
+
+const SEARCH_CHUNK_LEN: usize = 0x100000;
+const SEARCH_CTX_LEN: usize = 4; //note bytes read after each chunk for checking word boundaries
+const SEARCH_RESYNC_LEN: usize = 0x1000; //note bytes read before each chunk for checking word boundaries and char boundaries
+const _: () = assert!(SEARCH_RESYNC_LEN >= SEARCH_CTX_LEN, "Constraint on const");
+const SEARCH_PROGRESS_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+const DEF_PP_LIMIT: usize = 0x10000; //note bytes from bookmark taken by pp
+const MIN_PP_LIMIT: usize = 16;
+const _: () = assert!(DEF_PP_LIMIT >= MIN_PP_LIMIT, "Constraint on const");
+const PP_INDENT: usize = 2;
+
+const LOG_RULES_FILENM: &str = "logrules";
+const DEF_LOG_RULES: &str = r#"#{SGR code} {regex}, e.g. 31 for red, 1;33 for bold yellow, a byte matched by an earlier rule is never colored by a later one
+1;31 \b(ERROR|FATAL|CRIT|CRITICAL)\b
+1;33 \b(WARN|WARNING)\b
+32 \bINFO\b
+2 \b(DEBUG|TRACE)\b
+36 \b\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}([.,]\d+)?)?(Z|[+-]\d{2}:?\d{2})?
+35 \b\d{1,3}(\.\d{1,3}){3}(:\d+)?\b
+34 \b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b
+33 "([^"\\\n]|\\.)*"
+"#;
+
+const TS_REGEX: &str = r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}([.,]\d+)?)?";
+const TS_PROBE_LEN: usize = 0x10000; //note bytes read from each probe of t for finding a timestamp
+
+const BS_PROBE_LEN: usize = 0x1000; //note bytes read from each probe of bs, longer keys are compared by their heads
+
+const BOOKMARK_CTX_LEN: usize = 32; //note bytes around bookmark whose hash is saved for relocating bookmark
+const BOOKMARK_HASH_LEN: usize = 8;
+const BOOKMARK_RELOCATE_RANGE: u64 = 0x10000; //note how far bookmark can be relocated (both directions)
+
+const CSV_HEADER_LIMIT: u64 = 0x100000; //note header names are only taken from this many bytes of the first record
+const CSV_NAME_LIMIT: u64 = 64;
+
 const DEF_OLDFILES_LST_LEN: usize = 20; //todo make this configurable
 
 fn main() -> ExitCode {
@@ -99,9 +178,55 @@ fn must_be_ge_otherwise_err<const N: usize>(newval: usize, msg: &'static str) ->
     }
     dummy_err(msg)
 }
+static WINCH: sync::atomic::AtomicBool = sync::atomic::AtomicBool::new(false);
+static INTERRUPTED: sync::atomic::AtomicBool = sync::atomic::AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_sigwinch(_: libc::c_int) {
+    WINCH.store(true, sync::atomic::Ordering::Relaxed);
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, sync::atomic::Ordering::Relaxed);
+}
+
+//note returns columns and rows, None if stdout is not a terminal
+#[cfg(unix)]
+fn term_size() -> Option<(usize, usize)> {
+    let mut wsz: libc::winsize = unsafe { mem::zeroed() };
+    let res = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut wsz) };
+    if res != 0 || wsz.ws_col == 0 || wsz.ws_row == 0 {
+        return None;
+    }
+    Some((wsz.ws_col as usize, wsz.ws_row as usize))
+}
+#[cfg(not(unix))]
+fn term_size() -> Option<(usize, usize)> {
+    None
+}
+
+fn fit_term_size(con: &mut Ctx) {
+    if let Some((cols, rows)) = term_size() {
+        fit_size(con, cols, rows);
+    }
+}
+
+//note display size set by env var is never overridden
+fn fit_size(con: &mut Ctx, cols: usize, rows: usize) {
+    if !con.def.dlwidth_pinned {
+        con.def_dlwidth = cmp::max(MIN_DISPLAY_LINE_WIDTH, cols);
+    }
+    if !con.def.dheight_pinned {
+        let reserved = TERM_RESERVED_ROWS + con.def.status as usize;
+        con.def_dheight = cmp::max(MIN_DISPLAY_HEIGHT, rows.saturating_sub(reserved));
+    }
+}
+
 fn main_inner(args: Vec<String>) -> CustRes<()> {
     //use rusqlite::Connection;
     use sha2::Digest;
+    use std::io::IsTerminal;
     //let conn = Connection::open_in_memory()?;
 
     let mut ctx = Ctx {
@@ -112,19 +237,33 @@ fn main_inner(args: Vec<String>) -> CustRes<()> {
         def: CtxDef::default(),
     };
     ctx.def_dlwidth = match env::var("GMTC_DEF_DISPLAY_LINE_WIDTH") {
-        Ok(vstr) => must_be_ge_otherwise_err::<MIN_DISPLAY_LINE_WIDTH>(
-            vstr.parse()?,
-            "GMTC_DEF_DISPLAY_LINE_WIDTH is too small",
-        )?,
+        Ok(vstr) => {
+            ctx.def.dlwidth_pinned = true;
+            must_be_ge_otherwise_err::<MIN_DISPLAY_LINE_WIDTH>(
+                vstr.parse()?,
+                "GMTC_DEF_DISPLAY_LINE_WIDTH is too small",
+            )?
+        }
         Err(_) => DEF_DISPLAY_LINE_WIDTH,
     };
     ctx.def_dheight = match env::var("GMTC_DEF_DISPLAY_HEIGHT") {
-        Ok(vstr) => must_be_ge_otherwise_err::<MIN_DISPLAY_HEIGHT>(
-            vstr.parse()?,
-            "GMTC_DEF_DISPLAY_HEIGHT is too small",
-        )?,
+        Ok(vstr) => {
+            ctx.def.dheight_pinned = true;
+            must_be_ge_otherwise_err::<MIN_DISPLAY_HEIGHT>(
+                vstr.parse()?,
+                "GMTC_DEF_DISPLAY_HEIGHT is too small",
+            )?
+        }
         Err(_) => DEF_DISPLAY_HEIGHT,
     };
+    fit_term_size(&mut ctx);
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(
+            libc::SIGWINCH,
+            on_sigwinch as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
     ctx.def_wind_size = match env::var("GMTC_DEF_WIND_SIZE") {
         Ok(vstr) => must_be_ge_otherwise_err::<MIN_WIND_SIZE>(
             vstr.parse()?,
@@ -139,6 +278,25 @@ fn main_inner(args: Vec<String>) -> CustRes<()> {
         )?,
         Err(_) => DEF_CACHE_SIZE,
     };
+    ctx.def.tabstop = match env::var("GMTC_DEF_TABSTOP") {
+        Ok(vstr) => {
+            must_be_ge_otherwise_err::<MIN_TABSTOP>(vstr.parse()?, "GMTC_DEF_TABSTOP is too small")?
+        }
+        Err(_) => DEF_TABSTOP,
+    };
+    if ctx.def.tabstop > MAX_TABSTOP {
+        return dummy_err("GMTC_DEF_TABSTOP is too large");
+    }
+    ctx.def.bs_delim = b'\t';
+    ctx.def.pp_limit = match env::var("GMTC_DEF_PP_LIMIT") {
+        Ok(vstr) => must_be_ge_otherwise_err::<MIN_PP_LIMIT>(
+            vstr.parse()?,
+            "GMTC_DEF_PP_LIMIT is too small",
+        )?,
+        Err(_) => DEF_PP_LIMIT,
+    };
+    //note escape sequences would end up as garbage when output is redirected
+    ctx.def.hl = io::stdout().is_terminal();
     ctx.def_enc_scheme = match env::var("GMTC_DEF_ENCODING_SCHEME") {
         Ok(vstr) => vstr,
         Err(_) => "utf-8".into(),
@@ -191,63 +349,110 @@ fn main_inner(args: Vec<String>) -> CustRes<()> {
             }
             Some(Ok(linestr)) => linestr,
         };
-        macro_rules! if_no_file_then_noop {
-            () => {
-                if ctx.def.fsmd.is_none() {
-                    coutln!("No file opened.");
-                    continue;
-                }
-            };
+        if WINCH.swap(false, sync::atomic::Ordering::Relaxed) {
+            fit_term_size(&mut ctx);
         }
-        match ctx.def.iline.as_str() {
-            "exit" | "quit" => {
-                break Ok(());
+        if !run_cmd(&mut ctx)? {
+            break Ok(());
+        }
+    };
+    write_bookmark(&mut ctx)?;
+    retval
+}
+
+//note returns false if program should exit
+fn run_cmd(con: &mut Ctx) -> CustRes<bool> {
+    macro_rules! if_no_file_then_noop {
+        () => {
+            if con.def.fsmd.is_none() {
+                coutln!("No file opened.");
+                return Ok(true);
             }
-            "ol" | "oldfiles" => {
-                write_bookmark(&mut ctx)?;
-                if !oldfiles(&mut ctx)? {
-                    break Ok(());
-                }
+        };
+    }
+    match con.def.iline.as_str() {
+        "exit" | "quit" => {
+            return Ok(false);
+        }
+        "ol" | "oldfiles" => {
+            if con.def.in_tui {
+                coutln!("Not available in full-screen mode.");
+                return Ok(true);
             }
-            "g" => {
-                //similar to vim ctrl+g
-                if_no_file_then_noop!();
-                cmd_g(&ctx);
+            write_bookmark(con)?;
+            if !oldfiles(con)? {
+                return Ok(false);
             }
-            "rev" => {
-                eq_exclam!(ctx.def.reversed);
-                println!("{}{}", "rev == ", ctx.def.reversed);
+        }
+        "g" => {
+            //similar to vim ctrl+g
+            if_no_file_then_noop!();
+            cmd_g(con);
+        }
+        "rev" => {
+            eq_exclam!(con.def.reversed);
+            println!("{}{}", "rev == ", con.def.reversed);
+        }
+        "tui" => {
+            if_no_file_then_noop!();
+            return tui(con);
+        }
+        "jm" | "jn" | "ju" => {
+            if_no_file_then_noop!();
+            cmd_json(con)?;
+        }
+        "pp" | "pp json" | "pp xml" => {
+            if_no_file_then_noop!();
+            cmd_pp(con)?;
+        }
+        "+" | " " => {
+            if_no_file_then_noop!();
+            con.def.bookmark_end = con.def.bookmark;
+            show_prev_page(con)?;
+        }
+        "" => {
+            if_no_file_then_noop!();
+            if con.def.reversed {
+                con.def.bookmark_end = con.def.bookmark;
+                show_prev_page(con)?;
+            } else {
+                con.def.bookmark = con.def.bookmark_end;
+                show_page(con)?;
             }
-            "+" | " " => {
+        }
+        _ => {
+            if con.def.iline.starts_with("/") {
                 if_no_file_then_noop!();
-                ctx.def.bookmark_end = ctx.def.bookmark;
-                show_prev_page(&mut ctx)?;
-            }
-            "" => {
+                search_bytes(con)?;
+            } else if con.def.iline.starts_with("count /") {
                 if_no_file_then_noop!();
-                if ctx.def.reversed {
-                    ctx.def.bookmark_end = ctx.def.bookmark;
-                    show_prev_page(&mut ctx)?;
-                } else {
-                    ctx.def.bookmark = ctx.def.bookmark_end;
-                    show_page(&mut ctx)?;
-                }
-            }
-            _ => {
-                if ctx.def.iline.starts_with("/") {
-                    if_no_file_then_noop!();
-                    search_bytes(&mut ctx)?;
-                } else if ctx.def.iline.ends_with("%") {
-                    if_no_file_then_noop!();
-                    percentage_wise(&mut ctx)?;
-                } else {
-                    coutln!("Command not recognized.");
-                }
+                cmd_count(con)?;
+            } else if con.def.iline.starts_with("bs ") {
+                if_no_file_then_noop!();
+                cmd_bs(con)?;
+            } else if con.def.iline.starts_with("t ") {
+                if_no_file_then_noop!();
+                cmd_t(con)?;
+            } else if con.def.iline.starts_with("c ") {
+                if_no_file_then_noop!();
+                cmd_c(con)?;
+            } else if con.def.iline.starts_with("&") {
+                if_no_file_then_noop!();
+                cmd_filter(con)?;
+            } else if con.def.iline.starts_with("?") {
+                if_no_file_then_noop!();
+                search_bytes_rev(con)?;
+            } else if con.def.iline.starts_with("se ") || con.def.iline.starts_with("set ") {
+                cmd_se(con)?;
+            } else if con.def.iline.ends_with("%") {
+                if_no_file_then_noop!();
+                percentage_wise(con)?;
+            } else {
+                coutln!("Command not recognized.");
             }
         }
-    };
-    write_bookmark(&mut ctx)?;
-    retval
+    }
+    Ok(true)
 }
 
 fn write_bookmark(con: &mut Ctx) -> CustRes<()> {
@@ -258,15 +463,120 @@ fn write_bookmark(con: &mut Ctx) -> CustRes<()> {
         if !ok {
             return Err(CustomErr {});
         }
+        let md = con.def.fsfile.as_ref().unwrap().metadata()?;
+        let (ctx_before, ctx) = bookmark_ctx(con, con.def.bookmark, md.len())?;
+        let hash = short_hash(con, &ctx);
+        let rec = format!(
+            "{} {} {} {} {} {}",
+            con.def.bookmark,
+            md.len(),
+            mtime_nanos(&md),
+            ctx_before,
+            ctx.len(),
+            hash
+        );
         //fixme `update_open_time_to_now` acquires lock but release it before fs::write. You should release after fs::write. Such that `write_bookmark` becomes really atomic
-        fs::write(
-            &con.def.text_file_bookmark_path,
-            con.def.bookmark.to_string(),
-        )?;
+        fs::write(&con.def.text_file_bookmark_path, rec)?;
     }
     Ok(())
 }
 
+//note bookmark file holds offset, file size, mtime and where and what (as a short hash) the bytes around offset are, so that the offset can be relocated if the file is edited
+struct BookmarkRec {
+    size: u64,
+    mtime: u128,
+    ctx_before: u64, //note bytes of context before offset
+    ctx_len: usize,
+    hash: String,
+}
+
+//note returns None for bookmark file of older versions (offset only)
+fn parse_bookmark(txt: &str) -> CustRes<(u64, Option<BookmarkRec>)> {
+    let mut fields = txt.split_whitespace();
+    let off = fields.next().unwrap_or_default().parse()?;
+    let rest: Vec<&str> = fields.collect();
+    if rest.len() != 5 {
+        return Ok((off, None));
+    }
+    let rec = BookmarkRec {
+        size: rest[0].parse()?,
+        mtime: rest[1].parse()?,
+        ctx_before: rest[2].parse()?,
+        ctx_len: rest[3].parse()?,
+        hash: rest[4].to_owned(),
+    };
+    Ok((off, Some(rec)))
+}
+
+fn mtime_nanos(md: &fs::Metadata) -> u128 {
+    md.modified()
+        .ok()
+        .and_then(|tim| tim.duration_since(time::UNIX_EPOCH).ok())
+        .map_or(0, |dur| dur.as_nanos())
+}
+
+//note returns bytes around off (half of BOOKMARK_CTX_LEN before it if possible) and how many of them are before off
+fn bookmark_ctx(con: &mut Ctx, off: u64, file_len: u64) -> CustRes<(u64, Vec<u8>)> {
+    use std::io::*;
+    let off = cmp::min(off, file_len);
+    let lo = off.saturating_sub(BOOKMARK_CTX_LEN as u64 / 2);
+    let hi = cmp::min(file_len, lo + BOOKMARK_CTX_LEN as u64);
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut buf = vec![0; (hi - lo) as usize];
+    fil.seek(io::SeekFrom::Start(lo))?;
+    fil.read_exact(&mut buf)?;
+    Ok((off - lo, buf))
+}
+
+fn short_hash(con: &mut Ctx, cont: &[u8]) -> String {
+    use sha2::Digest;
+    con.hasher.update(cont);
+    let digest = con.hasher.finalize_reset();
+    digest[..BOOKMARK_HASH_LEN]
+        .iter()
+        .map(|byt| format!("{:02x}", byt))
+        .collect()
+}
+
+//note searches outward from off (nearest first) within BOOKMARK_RELOCATE_RANGE for the bytes the bookmark was taken from
+fn relocate_bookmark(con: &mut Ctx, off: u64, rec: &BookmarkRec) -> CustRes<Option<u64>> {
+    use std::io::*;
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    if rec.ctx_len == 0 || (rec.ctx_len as u64) > file_len {
+        return Ok(None);
+    }
+    //note candidate positions of context start
+    let center = cmp::min(
+        off.saturating_sub(rec.ctx_before),
+        file_len - rec.ctx_len as u64,
+    );
+    let lo = center.saturating_sub(BOOKMARK_RELOCATE_RANGE);
+    let hi = cmp::min(
+        file_len - rec.ctx_len as u64,
+        center + BOOKMARK_RELOCATE_RANGE,
+    );
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut buf = vec![0; (hi - lo) as usize + rec.ctx_len];
+    fil.seek(io::SeekFrom::Start(lo))?;
+    fil.read_exact(&mut buf)?;
+    for dist in 0..=BOOKMARK_RELOCATE_RANGE {
+        let cands = [center.checked_add(dist), center.checked_sub(dist)];
+        for cand in cands.into_iter().flatten() {
+            if cand < lo || cand > hi {
+                continue;
+            }
+            let idx = (cand - lo) as usize;
+            if short_hash(con, &buf[idx..idx + rec.ctx_len]) == rec.hash {
+                return Ok(Some(cand + rec.ctx_before));
+            }
+        }
+        if center < lo + dist && center + dist > hi {
+            break;
+        }
+    }
+    Ok(None)
+}
+
 fn big_enough_buf_size(con: &Ctx) -> usize {
     //note when do a PgUp the buffer boundary might not fall on code point boundary, so give it a bit more size
     return 256
@@ -276,7 +586,7 @@ fn big_enough_buf_size(con: &Ctx) -> usize {
         );
 }
 
-trait TextRdr {
+trait TextRdr: Send + Sync {
     fn clone(&self) -> Box<dyn TextRdr>;
     fn chk_bom(&self, con: &mut Ctx) -> CustRes<u64>;
     fn render(&self, buf: &[u8], rlen: usize, con: &Ctx) -> usize;
@@ -284,7 +594,9 @@ trait TextRdr {
     //fn render_p(&self, con: &Ctx, strs: Vec<(usize, String)>, at_edge: bool) -> usize;
 
     //fn buf2str<'a>(&self, buf: &'a [u8], rlen: usize) -> borrow::Cow<'a, str>;
-    fn buf2strs(&self, buf: &[u8], rlen: usize, at_edge: bool) -> Vec<(usize, String)>; //note return chars and their offset
+    fn buf2strs(&self, buf: &[u8], rlen: usize, con: &Ctx, at_edge: bool) -> Vec<(usize, String)>; //note return chars and their offset
+    fn sync_before(&self, cont: &[u8], idx: usize) -> Option<usize>; //note return nearest char boundary at or before idx that can be told without knowing anything before cont
+    fn chr_len(&self, cont: &[u8], idx: usize) -> usize; //note return byte length of the char starting at idx (1 for invalid byte)
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -325,15 +637,26 @@ impl TextRdr for UTF8Rdr {
         let mut lin: String = " ".to_owned();
         let mut height = 0;
         let mut lin_width = 1;
+        let dlwidth = text_width(con);
+        let mut lstart = con.def.page_at_lstart;
+        let mut nl_before = con.def.page_nl;
+        let mut lin_off: Option<usize> = None; //note offset of first char of display line
         macro_rules! reset_lin {
             ($new_chr: expr) => {
                 lin = $new_chr.to_owned();
                 lin_width = 1;
+                lin_off = None;
+                //note "$" means a newline has just been consumed
+                lstart = $new_chr == "$";
+                if lstart {
+                    nl_before = nl_before.map(|cnt| cnt + 1);
+                }
             };
         }
         macro_rules! cout_set_retval {
             () => {
-                coutln!(lin);
+                let dline_off = con.def.bookmark + lin_off.unwrap_or(off) as u64;
+                cout_dline(con, &(gutter(con, lstart, nl_before, dline_off) + &lin));
                 retval = off;
             };
         }
@@ -344,15 +667,27 @@ impl TextRdr for UTF8Rdr {
                 break;
             };
         }
-        macro_rules! if_full_then_cout_reset {
-            () => {
-                if lin_width == con.def_dlwidth {
+        //note a display line is only flushed when the next char does not fit, so zero-width chars (e.g. combining marks) stay with the char before them
+        macro_rules! push_cell {
+            ($cstr: expr, $len: expr, $width: expr) => {
+                if lin_width + $width > dlwidth {
                     cout_reset!();
                 }
+                lin_off.get_or_insert(off);
+                push_hl(con, &mut lin, con.def.bookmark + off as u64, $cstr);
+                off += $len;
+                lin_width += $width;
+            };
+        }
+        macro_rules! push_rep_continue {
+            ($rep: expr, $len: expr) => {
+                push_cell!(&$rep, $len, disp_width(con, &$rep));
+                continue;
             };
         }
         //note do not forget the possibility of trailing invalid utf8 bytes or trailing broken stuff
         let mut depleted = false;
+        let at_eof = rlen < buf.len();
         'height_loop: loop {
             macro_rules! cout_if_needed_break_height {
                 () => {
@@ -363,6 +698,17 @@ impl TextRdr for UTF8Rdr {
                     break 'height_loop;
                 };
             }
+            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+            macro_rules! cout_truncated_or_break_height {
+                () => {
+                    if at_eof {
+                        if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                            push_rep_continue!(rep, cont.len() - off);
+                        }
+                    }
+                    cout_if_needed_break_height!();
+                };
+            }
             loop {
                 let byt: u8 = match cont.get(off) {
                     None => {
@@ -372,6 +718,12 @@ impl TextRdr for UTF8Rdr {
                 };
                 match byt {
                     b'\r' => {
+                        //note \r of CRLF is part of line ending so it is never visualized
+                        if cont.get(off + 1).is_some_and(|nxt| *nxt != b'\n') {
+                            if let Some(rep) = ctrl_repr(con, byt) {
+                                push_rep_continue!(rep, 1);
+                            }
+                        }
                         off += 1;
                     }
                     b'\n' => {
@@ -384,59 +736,58 @@ impl TextRdr for UTF8Rdr {
                         reset_lin!("$");
                         off += 1;
                     }
+                    b'\t' => {
+                        //note a tab right after a full display line starts the next one, so its width is counted from there
+                        if lin_width >= dlwidth {
+                            cout_reset!();
+                        }
+                        let tab_w = tab_width(con, lin_width);
+                        push_cell!(&" ".repeat(tab_w), 1, tab_w);
+                    }
                     0..=127 => {
+                        if let Some(rep) = ctrl_repr(con, byt) {
+                            push_rep_continue!(rep, 1);
+                        }
+                        if lin_width + 1 > dlwidth {
+                            cout_reset!();
+                        }
+                        lin_off.get_or_insert(off);
+                        let cstr = (byt as char).encode_utf8(&mut [0; 4]).to_owned();
+                        push_hl(con, &mut lin, con.def.bookmark + off as u64, &cstr);
                         off += 1;
-                        lin.push(byt as char);
                         lin_width += 1;
-                        if_full_then_cout_reset!();
                     }
                     //110xxxxx for 2-byte code point
-                    0b11000000..=0b11011111 => {
-                        let byte1: u8 = match cont.get(off + 1) {
-                            None => {
-                                cout_if_needed_break_height!();
-                            }
-                            Some(inner) => *inner,
-                        };
-                        off += 2;
-                        lin.push_str(&String::from_utf8_lossy(&[byt, byte1]));
-                        lin_width += 1;
-                        if_full_then_cout_reset!();
-                    }
                     //1110xxxx for 3-byte code point
-                    0b11100000..=0b11101111 => {
-                        if lin_width + 2 > con.def_dlwidth {
-                            cout_reset!();
-                        }
-                        let blob = match cont.get(off..off + 3) {
+                    //11110xxx for 4-byte code point
+                    0b11000000..=0b11110111 => {
+                        let seq_len = match byt {
+                            0b11000000..=0b11011111 => 2,
+                            0b11100000..=0b11101111 => 3,
+                            _ => 4,
+                        };
+                        let blob = match cont.get(off..off + seq_len) {
                             None => {
-                                cout_if_needed_break_height!();
+                                cout_truncated_or_break_height!();
                             }
                             Some(inner) => inner,
                         };
-                        off += 3;
-                        lin.push_str(&String::from_utf8_lossy(blob));
-                        lin_width += 2;
-                        if_full_then_cout_reset!();
-                    }
-                    //11110xxx for 4-byte code point
-                    0b11110000..=0b11110111 => {
-                        if lin_width + 2 > con.def_dlwidth {
-                            cout_reset!();
-                        }
-                        let blob = match cont.get(off..off + 4) {
-                            None => {
-                                cout_if_needed_break_height!();
+                        let cstr = match str::from_utf8(blob) {
+                            Ok(inner) => borrow::Cow::Borrowed(inner),
+                            Err(_) => {
+                                if let Some(rep) = invalid_repr(con, &[byt]) {
+                                    push_rep_continue!(rep, 1);
+                                }
+                                String::from_utf8_lossy(blob)
                             }
-                            Some(inner) => inner,
                         };
-                        off += 4;
-                        lin.push_str(&String::from_utf8_lossy(blob));
-                        lin_width += 2;
-                        if_full_then_cout_reset!();
+                        push_cell!(&cstr, seq_len, disp_width(con, &cstr));
                     }
                     _ => {
                         //info!("{}", "BROKEN ENCODED TEXT DETECT");
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            push_rep_continue!(rep, 1);
+                        }
                         off += 1;
                     }
                 }
@@ -448,11 +799,11 @@ impl TextRdr for UTF8Rdr {
         }
         if rlen < buf.len() && depleted {
             //? maybe hold back this println if height already full?
-            coutln!("END-OF-FILE");
+            cout_dline(con, "END-OF-FILE");
         }
         retval
     }
-    fn buf2strs(&self, buf: &[u8], rlen: usize, _at_edge: bool) -> Vec<(usize, String)> {
+    fn buf2strs(&self, buf: &[u8], rlen: usize, con: &Ctx, _at_edge: bool) -> Vec<(usize, String)> {
         let mut retval = vec![];
         let cont = &buf[0..rlen];
         let mut off = 0;
@@ -465,9 +816,19 @@ impl TextRdr for UTF8Rdr {
             };
             match byt {
                 b'\r' => {
+                    if cont.get(off + 1).is_some_and(|nxt| *nxt != b'\n') {
+                        if let Some(rep) = ctrl_repr(con, byt) {
+                            retval.push((off, rep));
+                        }
+                    }
                     off += 1;
                 }
                 0..=127 => {
+                    if let Some(rep) = ctrl_repr(con, byt) {
+                        retval.push((off, rep));
+                        off += 1;
+                        continue;
+                    }
                     let chr = byt as char;
                     retval.push((off, chr.into()));
                     off += 1;
@@ -476,10 +837,23 @@ impl TextRdr for UTF8Rdr {
                 0b11000000..=0b11011111 => {
                     let byte1: u8 = match cont.get(off + 1) {
                         None => {
+                            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+                            if rlen < buf.len() {
+                                if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                                    retval.push((off, rep));
+                                }
+                            }
                             break;
                         }
                         Some(inner) => *inner,
                     };
+                    if str::from_utf8(&[byt, byte1]).is_err() {
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            retval.push((off, rep));
+                            off += 1;
+                            continue;
+                        }
+                    }
                     let lstr = String::from_utf8_lossy(&[byt, byte1]).into_owned();
                     retval.push((off, lstr));
                     off += 2;
@@ -488,10 +862,23 @@ impl TextRdr for UTF8Rdr {
                 0b11100000..=0b11101111 => {
                     let blob = match cont.get(off..off + 3) {
                         None => {
+                            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+                            if rlen < buf.len() {
+                                if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                                    retval.push((off, rep));
+                                }
+                            }
                             break;
                         }
                         Some(inner) => inner,
                     };
+                    if str::from_utf8(blob).is_err() {
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            retval.push((off, rep));
+                            off += 1;
+                            continue;
+                        }
+                    }
                     let lstr = String::from_utf8_lossy(blob).into_owned();
                     retval.push((off, lstr));
                     off += 3;
@@ -500,22 +887,57 @@ impl TextRdr for UTF8Rdr {
                 0b11110000..=0b11110111 => {
                     let blob = match cont.get(off..off + 4) {
                         None => {
+                            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+                            if rlen < buf.len() {
+                                if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                                    retval.push((off, rep));
+                                }
+                            }
                             break;
                         }
                         Some(inner) => inner,
                     };
+                    if str::from_utf8(blob).is_err() {
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            retval.push((off, rep));
+                            off += 1;
+                            continue;
+                        }
+                    }
                     let lstr = String::from_utf8_lossy(blob).into_owned();
                     retval.push((off, lstr));
                     off += 4;
                 }
                 _ => {
                     //info!("{}", "BROKEN ENCODED TEXT DETECT");
+                    if let Some(rep) = invalid_repr(con, &[byt]) {
+                        retval.push((off, rep));
+                    }
                     off += 1;
                 }
             }
         }
         retval
     }
+    fn sync_before(&self, cont: &[u8], idx: usize) -> Option<usize> {
+        //note every byte except continuation bytes starts a char
+        let mut retval = idx;
+        while cont.get(retval)? & 0b11000000 == 0b10000000 {
+            if retval == 0 || idx - retval == 3 {
+                return None;
+            }
+            retval -= 1;
+        }
+        Some(retval)
+    }
+    fn chr_len(&self, cont: &[u8], idx: usize) -> usize {
+        match cont[idx] {
+            0b11000000..=0b11011111 => 2,
+            0b11100000..=0b11101111 => 3,
+            0b11110000..=0b11110111 => 4,
+            _ => 1,
+        }
+    }
     /*
     fn buf2str<'a>(&self, buf: &'a [u8], rlen: usize) -> borrow::Cow<'a, str> {
     //fixme this is not utf8
@@ -557,15 +979,26 @@ impl TextRdr for GB18030Rdr {
         let mut lin: String = " ".to_owned();
         let mut height = 0;
         let mut lin_width = 1;
+        let dlwidth = text_width(con);
+        let mut lstart = con.def.page_at_lstart;
+        let mut nl_before = con.def.page_nl;
+        let mut lin_off: Option<usize> = None; //note offset of first char of display line
         macro_rules! reset_lin {
             ($new_chr: expr) => {
                 lin = $new_chr.to_owned();
                 lin_width = 1;
+                lin_off = None;
+                //note "$" means a newline has just been consumed
+                lstart = $new_chr == "$";
+                if lstart {
+                    nl_before = nl_before.map(|cnt| cnt + 1);
+                }
             };
         }
         macro_rules! cout_set_retval {
             () => {
-                coutln!(lin);
+                let dline_off = con.def.bookmark + lin_off.unwrap_or(off) as u64;
+                cout_dline(con, &(gutter(con, lstart, nl_before, dline_off) + &lin));
                 retval = off;
             };
         }
@@ -576,15 +1009,27 @@ impl TextRdr for GB18030Rdr {
                 break;
             };
         }
-        macro_rules! if_full_then_cout_reset {
-            () => {
-                if lin_width == con.def_dlwidth {
+        //note a display line is only flushed when the next char does not fit, so zero-width chars (e.g. combining marks) stay with the char before them
+        macro_rules! push_cell {
+            ($cstr: expr, $len: expr, $width: expr) => {
+                if lin_width + $width > dlwidth {
                     cout_reset!();
                 }
+                lin_off.get_or_insert(off);
+                push_hl(con, &mut lin, con.def.bookmark + off as u64, $cstr);
+                off += $len;
+                lin_width += $width;
+            };
+        }
+        macro_rules! push_rep_continue {
+            ($rep: expr, $len: expr) => {
+                push_cell!(&$rep, $len, disp_width(con, &$rep));
+                continue;
             };
         }
         //note do not forget the possibility of trailing invalid utf8 bytes or trailing broken stuff
         let mut depleted = false;
+        let at_eof = rlen < buf.len();
         'height_loop: loop {
             macro_rules! cout_if_needed_break_height {
                 () => {
@@ -595,6 +1040,17 @@ impl TextRdr for GB18030Rdr {
                     break 'height_loop;
                 };
             }
+            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+            macro_rules! cout_truncated_or_break_height {
+                () => {
+                    if at_eof {
+                        if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                            push_rep_continue!(rep, cont.len() - off);
+                        }
+                    }
+                    cout_if_needed_break_height!();
+                };
+            }
             loop {
                 let byt: u8 = match cont.get(off) {
                     None => {
@@ -604,6 +1060,12 @@ impl TextRdr for GB18030Rdr {
                 };
                 match byt {
                     b'\r' => {
+                        //note \r of CRLF is part of line ending so it is never visualized
+                        if cont.get(off + 1).is_some_and(|nxt| *nxt != b'\n') {
+                            if let Some(rep) = ctrl_repr(con, byt) {
+                                push_rep_continue!(rep, 1);
+                            }
+                        }
                         off += 1;
                     }
                     b'\n' => {
@@ -616,56 +1078,73 @@ impl TextRdr for GB18030Rdr {
                         reset_lin!("$");
                         off += 1;
                     }
+                    b'\t' => {
+                        //note a tab right after a full display line starts the next one, so its width is counted from there
+                        if lin_width >= dlwidth {
+                            cout_reset!();
+                        }
+                        let tab_w = tab_width(con, lin_width);
+                        push_cell!(&" ".repeat(tab_w), 1, tab_w);
+                    }
                     0..=127 => {
+                        if let Some(rep) = ctrl_repr(con, byt) {
+                            push_rep_continue!(rep, 1);
+                        }
+                        if lin_width + 1 > dlwidth {
+                            cout_reset!();
+                        }
+                        lin_off.get_or_insert(off);
+                        let cstr = (byt as char).encode_utf8(&mut [0; 4]).to_owned();
+                        push_hl(con, &mut lin, con.def.bookmark + off as u64, &cstr);
                         off += 1;
-                        lin.push(byt as char);
                         lin_width += 1;
-                        if_full_then_cout_reset!();
                     }
                     //invalid
                     0x80 | 0xFF => {
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            push_rep_continue!(rep, 1);
+                        }
                         off += 1;
                     }
                     0x81..=0xFE => {
-                        if lin_width + 2 > con.def_dlwidth {
-                            cout_reset!();
-                        }
                         let byte1: u8 = match cont.get(off + 1) {
                             None => {
-                                cout_if_needed_break_height!();
+                                cout_truncated_or_break_height!();
                             }
                             Some(inner) => *inner,
                         };
                         match byte1 {
                             //invalid
                             0x7F | 0xFF => {
+                                if let Some(rep) = invalid_repr(con, &[byt, byte1]) {
+                                    push_rep_continue!(rep, 2);
+                                }
                                 off += 2;
                                 continue;
                             }
                             0x40..=0xFE => {
-                                off += 2;
                                 use encoding_rs::*;
                                 let blob = [byt, byte1];
                                 let (cow, _encoding_used, _had_errors) = GB18030.decode(&blob);
-                                lin.push_str(&cow);
-                                lin_width += 2;
-                                if_full_then_cout_reset!();
+                                push_cell!(&cow, 2, disp_width(con, &cow));
                                 continue;
                             }
                             _ => {}
                         }
                         let blob = match cont.get(off..off + 4) {
                             None => {
-                                cout_if_needed_break_height!();
+                                cout_truncated_or_break_height!();
                             }
                             Some(inner) => inner,
                         };
-                        off += 4;
                         use encoding_rs::*;
-                        let (cow, _encoding_used, _had_errors) = GB18030.decode(blob);
-                        lin.push_str(&cow);
-                        lin_width += 2;
-                        if_full_then_cout_reset!();
+                        let (cow, _encoding_used, had_errors) = GB18030.decode(blob);
+                        if had_errors {
+                            if let Some(rep) = invalid_repr(con, &[byt]) {
+                                push_rep_continue!(rep, 1);
+                            }
+                        }
+                        push_cell!(&cow, 4, disp_width(con, &cow));
                     }
                 }
             }
@@ -676,11 +1155,11 @@ impl TextRdr for GB18030Rdr {
         }
         if rlen < buf.len() && depleted {
             //? maybe hold back this println if height already full?
-            coutln!("END-OF-FILE");
+            cout_dline(con, "END-OF-FILE");
         }
         retval
     }
-    fn buf2strs(&self, buf: &[u8], rlen: usize, at_edge: bool) -> Vec<(usize, String)> {
+    fn buf2strs(&self, buf: &[u8], rlen: usize, con: &Ctx, _at_edge: bool) -> Vec<(usize, String)> {
         use encoding_rs::*;
         let mut retval: Vec<(usize, String)> = vec![];
         let cont = &buf[0..rlen];
@@ -709,21 +1188,42 @@ impl TextRdr for GB18030Rdr {
             };
             match byt {
                 b'\r' => {
+                    if cont.get(off + 1).is_some_and(|nxt| *nxt != b'\n') {
+                        if let Some(rep) = ctrl_repr(con, byt) {
+                            retval.push((off, rep));
+                        }
+                    }
                     off += 1;
                 }
                 0..=127 => {
+                    if let Some(rep) = ctrl_repr(con, byt) {
+                        retval.push((off, rep));
+                        off += 1;
+                        continue;
+                    }
                     let chr = byt as char;
                     retval.push((off, chr.into()));
                     off += 1;
                 }
                 //invalid
                 0x80 | 0xFF => {
+                    if let Some(rep) = invalid_repr(con, &[byt]) {
+                        retval.push((off, rep));
+                        off += 1;
+                        continue;
+                    }
                     retval.push((off, "\u{FFFD}".into()));
                     off += 1;
                 }
                 0x81..=0xFE => {
                     let byte1: u8 = match cont.get(off + 1) {
                         None => {
+                            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+                            if rlen < buf.len() {
+                                if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                                    retval.push((off, rep));
+                                }
+                            }
                             break;
                         }
                         Some(inner) => *inner,
@@ -731,6 +1231,11 @@ impl TextRdr for GB18030Rdr {
                     match byte1 {
                         //invalid
                         0x7F | 0xFF => {
+                            if let Some(rep) = invalid_repr(con, &[byt, byte1]) {
+                                retval.push((off, rep));
+                                off += 2;
+                                continue;
+                            }
                             retval.push((off, "\u{FFFD}\u{FFFD}".into()));
                             off += 2;
                             continue;
@@ -746,11 +1251,24 @@ impl TextRdr for GB18030Rdr {
                     }
                     let blob = match cont.get(off..off + 4) {
                         None => {
+                            //note a multi-byte sequence cut by the end of file is never completed, so it is shown as invalid instead of being dropped
+                            if rlen < buf.len() {
+                                if let Some(rep) = invalid_repr(con, &cont[off..]) {
+                                    retval.push((off, rep));
+                                }
+                            }
                             break;
                         }
                         Some(inner) => inner,
                     };
-                    let (cow, _encoding_used, _had_errors) = GB18030.decode(blob);
+                    let (cow, _encoding_used, had_errors) = GB18030.decode(blob);
+                    if had_errors {
+                        if let Some(rep) = invalid_repr(con, &[byt]) {
+                            retval.push((off, rep));
+                            off += 1;
+                            continue;
+                        }
+                    }
                     retval.push((off, cow.into()));
                     off += 4;
                 }
@@ -758,6 +1276,20 @@ impl TextRdr for GB18030Rdr {
         }
         retval
     }
+    fn sync_before(&self, cont: &[u8], idx: usize) -> Option<usize> {
+        //note bytes below 0x30 are never trailing bytes, so a char always starts right after them
+        cont[..idx]
+            .iter()
+            .rposition(|byt| *byt < 0x30)
+            .map(|pos| pos + 1)
+    }
+    fn chr_len(&self, cont: &[u8], idx: usize) -> usize {
+        match (cont[idx], cont.get(idx + 1)) {
+            (0x81..=0xFE, Some(0x40..=0xFF)) => 2,
+            (0x81..=0xFE, Some(0x30..=0x39)) => 4,
+            _ => 1,
+        }
+    }
     /*
     fn buf2str<'a>(&self, buf: &'a [u8], rlen: usize) -> borrow::Cow<'a, str> {
     //fixme this is not utf8
@@ -767,6 +1299,22 @@ impl TextRdr for GB18030Rdr {
 
 fn show_prev_page(con: &mut Ctx) -> CustRes<()> {
     use std::io::*;
+    if con.def.filter.is_some() && con.def.view_mode != ViewMode::Hex {
+        return show_filter_prev_page(con);
+    }
+    if con.def.csv.is_some() && con.def.view_mode != ViewMode::Hex {
+        return show_csv_prev_page(con);
+    }
+    if con.def.view_mode == ViewMode::Hex {
+        //note hex dump has fixed row length so there is no need to render backwards
+        if con.def.bookmark_end == 0 {
+            info!("{}", "Top of file reached.");
+            return Ok(());
+        }
+        let page_len = (con.def_dheight * hex_row_len(con)) as u64;
+        con.def.bookmark = con.def.bookmark_end.saturating_sub(page_len);
+        return show_page(con);
+    }
     let mut at_edge = false;
     let bufsize = big_enough_buf_size(con);
     let tr = con.tr.clone();
@@ -784,83 +1332,654 @@ fn show_prev_page(con: &mut Ctx) -> CustRes<()> {
         info!("{}", "Top of file reached.");
         return Ok(());
     }
+    if con.def.nu {
+        con.def.page_nl = nl_count_before(con, con.def.bookmark_end)?;
+    }
     let fil = con.def.fsfile.as_mut().unwrap();
     fil.seek(io::SeekFrom::Start(bm))?;
     //let mut buf = vec![0; bufsize];
     let mut buf = vec![0; (con.def.bookmark_end - bm) as usize];
     let rlen = read_to_buf(fil, &mut buf)?;
-    let mut strs = tr.buf2strs(&buf, rlen, at_edge);
+    let mut strs = tr.buf2strs(&buf, rlen, con, at_edge);
     strs.retain(|tup| !tup.1.is_empty());
+    if con.def.grapheme {
+        strs = group_graphemes(strs);
+    }
+    con.def.page_base = bm;
+    find_hits(con, &buf[..rlen], bm);
+    find_colors(con, &buf[..rlen], bm);
     if strs.is_empty() {
         //note this is reachable when e.g. you have crazy amount of consecutive \r (all characters ignored)
         con.def.bookmark = bm;
         info!("{}", "All characters in this page are non-printable");
         return Ok(());
     }
-    con.def.bookmark = bm + render_p(con, strs, at_edge) as u64;
+    let used_off = render_p(con, strs, at_edge);
+    con.def.bookmark = bm + used_off as u64;
+    if let (true, Some(cnt)) = (con.def.nu, con.def.page_nl) {
+        let nl_in_page = bytecount_nl(&buf[used_off..rlen]);
+        if let Some(nl_before) = cnt.checked_sub(nl_in_page) {
+            con.def.line_anchors.insert(con.def.bookmark, nl_before);
+        }
+    }
+    cout_status_if_needed(con);
     Ok(())
 }
 
-fn render_p(con: &Ctx, mut strs: Vec<(usize, String)>, _at_edge: bool) -> usize {
+fn render_p(con: &Ctx, mut strs: Vec<(usize, String)>, at_edge: bool) -> usize {
     let mut retval = 0;
-    let mut lin: String = "".to_owned();
-    let mut height = 1;
+    let dlwidth = text_width(con);
+    //note newlines before the end of page minus newlines seen so far is the count of newlines before current display line
+    let mut nl_after = 0;
+    let mut dlines: Vec<String> = vec![]; //note display lines from bottom to top
+    let mut cells: collections::VecDeque<(usize, String)> = collections::VecDeque::new();
+    let mut has_tab = false;
     let mut lin_width = 1;
+    let mut lin_off = 0; //note offset of first char of display line
     loop {
-        let (off, cstr) = match strs.pop() {
+        let (mut off, mut cstr) = match strs.pop() {
             None => {
                 break;
             }
             Some(inner) => inner,
         };
-        macro_rules! mk_newline {
-            () => {
-                lin.insert(0, '\n');
-                height += 1;
-                lin_width = 1;
-            };
-        }
-        macro_rules! endline_set_retval {
-            () => {
-                lin.insert(0, ' ');
-                if height == con.def_dheight {
+        macro_rules! endline {
+            ($marker: expr) => {
+                let lstart = $marker == "$";
+                let nl_before = con.def.page_nl.and_then(|cnt| cnt.checked_sub(nl_after));
+                let dline_off = con.def.page_base + lin_off as u64;
+                dlines.push(
+                    gutter(con, lstart, nl_before, dline_off) + &join_cells(con, $marker, &cells),
+                );
+                cells.clear();
+                if dlines.len() == con.def_dheight {
                     break;
                 }
-                mk_newline!();
             };
         }
-        macro_rules! endrealline_set_retval {
-            () => {
-                lin.insert(0, '$');
+        let mut chr_width = match cstr.as_str() {
+            "" => {
+                panic!("This should never be reachable");
+            }
+            "\n" => {
                 retval = off;
-                if height == con.def_dheight {
-                    break;
+                if cells.is_empty() {
+                    lin_off = off + 1;
                 }
-                mk_newline!();
+                endline!("$");
+                nl_after += 1;
+                has_tab = false;
+                lin_width = 1;
                 continue;
-            };
-        }
-        let chr_width = match cstr.len() {
-            0 => {
-                panic!("This should never be reachable");
             }
-            1 => {
-                if cstr == "\n" {
-                    endrealline_set_retval!();
-                }
+            "\t" => {
+                has_tab = true;
                 1
             }
-            2 => 1,
-            _ => 2,
+            _ => disp_width(con, &cstr),
+        };
+        //note zero-width chars (e.g. combining marks) are kept on the same display line as the char before them
+        while chr_width == 0 {
+            match strs.last() {
+                Some((_, prev)) if prev != "\n" && prev != "\t" => {}
+                _ => {
+                    break;
+                }
+            }
+            let (prev_off, prev_str) = strs.pop().unwrap();
+            cstr.insert_str(0, &prev_str);
+            off = prev_off;
+            chr_width = disp_width(con, &cstr);
+        }
+        //note tab width depends on the columns before it, which are unknown until the display line is complete, so recount
+        let mut new_width = if has_tab {
+            cells_width(
+                con,
+                iter::once(cstr.as_str()).chain(cells.iter().map(|tup| tup.1.as_str())),
+            )
+        } else {
+            lin_width + chr_width
         };
-        if lin_width + chr_width > con.def_dlwidth {
-            endline_set_retval!();
+        if new_width > dlwidth && !cells.is_empty() {
+            endline!(" ");
+            has_tab = cstr == "\t";
+            new_width = cells_width(con, iter::once(cstr.as_str()));
         }
-        lin_width += chr_width;
-        lin.insert_str(0, &cstr);
+        lin_width = new_width;
+        cells.push_front((off, cstr));
         retval = off;
+        lin_off = off;
+    }
+    if !cells.is_empty() {
+        //note at edge the remaining chars start from the first line of file
+        let nl_before = con.def.page_nl.and_then(|cnt| cnt.checked_sub(nl_after));
+        let dline_off = con.def.page_base + lin_off as u64;
+        dlines.push(gutter(con, at_edge, nl_before, dline_off) + &join_cells(con, " ", &cells));
+    }
+    for lin in dlines.iter().rev() {
+        cout_dline(con, lin);
+    }
+    retval
+}
+
+//note tab expands to next tab stop counted from the start of display line, but never beyond the end of display line
+fn tab_width(con: &Ctx, lin_width: usize) -> usize {
+    let retval = con.def.tabstop - (lin_width - 1) % con.def.tabstop;
+    let dlwidth = text_width(con);
+    if lin_width < dlwidth {
+        cmp::min(retval, dlwidth - lin_width)
+    } else {
+        retval
+    }
+}
+
+fn cells_width<'a>(con: &Ctx, cells: impl Iterator<Item = &'a str>) -> usize {
+    let mut lin_width = 1;
+    for cstr in cells {
+        lin_width += if cstr == "\t" {
+            tab_width(con, lin_width)
+        } else {
+            disp_width(con, cstr)
+        };
+    }
+    lin_width
+}
+
+//note offsets of cells are relative to page_base
+fn join_cells(con: &Ctx, marker: &str, cells: &collections::VecDeque<(usize, String)>) -> String {
+    let mut lin = marker.to_owned();
+    let mut lin_width = 1;
+    for (off, cstr) in cells {
+        let hl_off = con.def.page_base + *off as u64;
+        if cstr == "\t" {
+            let cell_width = tab_width(con, lin_width);
+            push_hl(con, &mut lin, hl_off, &" ".repeat(cell_width));
+            lin_width += cell_width;
+        } else {
+            push_hl(con, &mut lin, hl_off, cstr);
+            lin_width += disp_width(con, cstr);
+        }
+    }
+    lin
+}
+
+//note records matches of last search pattern in the page so that render functions can highlight them
+fn find_hits(con: &mut Ctx, cont: &[u8], base: u64) {
+    let mut hits = vec![];
+    if con.def.hl && !con.def.search_pat.blob.is_empty() {
+        let sync = known_sync(con, base);
+        let found = find_iter_in(
+            &*con.tr,
+            &con.def.search_pat,
+            cont,
+            0..cont.len(),
+            false,
+            sync,
+        );
+        hits = found
+            .map(|(idx, len)| (base + idx as u64, len as u64))
+            .collect();
+    }
+    con.def.page_hits = hits;
+}
+
+//note start of text (after BOM) is the only char boundary known for sure, return its position relative to base
+fn known_sync(con: &Ctx, base: u64) -> Option<usize> {
+    (base <= con.def.bom_end).then(|| (con.def.bom_end - base) as usize)
+}
+
+fn hl_at(con: &Ctx, off: u64) -> bool {
+    let idx = con.def.page_hits.partition_point(|hit| hit.0 <= off);
+    idx > 0 && off < con.def.page_hits[idx - 1].0 + con.def.page_hits[idx - 1].1
+}
+
+//note records spans of the page matched by log rules, a byte matched by an earlier rule is never taken by a later one
+fn find_colors(con: &mut Ctx, cont: &[u8], base: u64) {
+    let mut spans = vec![];
+    if con.def.loghl {
+        let mut owner = vec![usize::MAX; cont.len()];
+        for (idx, rule) in con.def.log_rules.iter().enumerate() {
+            for found in rule.0.find_iter(cont) {
+                if found.is_empty() || owner[found.range()].iter().any(|own| *own != usize::MAX) {
+                    continue;
+                }
+                owner[found.range()].fill(idx);
+                spans.push((base + found.start() as u64, base + found.end() as u64, idx));
+            }
+        }
+        spans.sort_unstable();
+    }
+    con.def.page_colors = spans;
+}
+
+//note returns start of the colored span containing off and its SGR code
+fn color_at(con: &Ctx, off: u64) -> Option<(u64, &str)> {
+    let idx = con.def.page_colors.partition_point(|span| span.0 <= off);
+    let span = con.def.page_colors.get(idx.checked_sub(1)?)?;
+    (off < span.1).then(|| (span.0, con.def.log_rules[span.2].1.as_str()))
+}
+
+//note rules file has one rule per line: SGR code (e.g. 31 for red, 1;33 for bold yellow) and regex separated by a space, # for comments
+fn load_log_rules(con: &mut Ctx) -> CustRes<()> {
+    let path = con.def.app_support_dir.join(LOG_RULES_FILENM);
+    if !path.try_exists()? {
+        fs::write(&path, DEF_LOG_RULES)?;
+    }
+    let mut rules = vec![];
+    for (idx, lin) in fs::read_to_string(&path)?.lines().enumerate() {
+        let lin = lin.trim();
+        if lin.is_empty() || lin.starts_with('#') {
+            continue;
+        }
+        let rule = lin.split_once(' ').and_then(|(code, ptn)| {
+            if code.is_empty() || !code.bytes().all(|byt| byt.is_ascii_digit() || byt == b';') {
+                return None;
+            }
+            let re = regex::bytes::Regex::new(ptn.trim()).ok()?;
+            Some((re, code.to_owned()))
+        });
+        match rule {
+            None => {
+                coutln!(format!("Log rule invalid on line {}.", idx + 1));
+            }
+            Some(inner) => {
+                rules.push(inner);
+            }
+        }
+    }
+    con.def.log_rules = rules;
+    Ok(())
+}
+
+//note reverse video is closed after each highlighted cell (and reopened only if the previous cell is not highlighted) so that a match spanning display lines never leaks into the gutter
+//note color is closed after each colored cell too (reset after reverse video is closed), and reopened only if the previous cell is not in the same span
+fn push_hl(con: &Ctx, lin: &mut String, off: u64, cstr: &str) {
+    let color = color_at(con, off);
+    if let Some((start, code)) = color {
+        match lin.strip_suffix("\x1b[0m") {
+            Some(inner) if start < off => lin.truncate(inner.len()),
+            _ => {
+                lin.push_str("\x1b[");
+                lin.push_str(code);
+                lin.push('m');
+            }
+        }
+    }
+    push_rev(con, lin, off, cstr);
+    if color.is_some() {
+        lin.push_str("\x1b[0m");
+    }
+}
+
+fn push_rev(con: &Ctx, lin: &mut String, off: u64, cstr: &str) {
+    if hl_at(con, off) {
+        match lin.strip_suffix("\x1b[27m") {
+            Some(inner) => lin.truncate(inner.len()),
+            None => lin.push_str("\x1b[7m"),
+        }
+        lin.push_str(cstr);
+        lin.push_str("\x1b[27m");
+    } else {
+        lin.push_str(cstr);
+    }
+}
+
+//note merges chars (with their offsets) into extended grapheme clusters
+fn group_graphemes(strs: Vec<(usize, String)>) -> Vec<(usize, String)> {
+    use unicode_segmentation::UnicodeSegmentation;
+    let joined: String = strs.iter().map(|tup| tup.1.as_str()).collect();
+    let mut bounds = joined.grapheme_indices(true).map(|(idx, _)| idx).peekable();
+    let mut retval: Vec<(usize, String)> = vec![];
+    let mut pos = 0;
+    for (off, cstr) in strs {
+        while bounds.peek().is_some_and(|bound| *bound < pos) {
+            bounds.next();
+        }
+        //note a boundary falling inside a multi-char representation (e.g. ^[) is ignored
+        let at_bound = bounds.peek() == Some(&pos);
+        pos += cstr.len();
+        match retval.last_mut() {
+            Some(last) if !at_bound => {
+                last.1.push_str(&cstr);
+            }
+            _ => {
+                retval.push((off, cstr));
+            }
+        }
+    }
+    retval
+}
+
+//note similar to TextRdr::render but display lines never break inside a grapheme cluster, slower
+fn render_g(con: &Ctx, mut strs: Vec<(usize, String)>, chars_end: usize, eof: bool) -> usize {
+    //note unless EOF is reached the last cluster might continue after the buffer, so leave it for next page
+    //note but a buffer holding one cluster only (e.g. zalgo text) is rendered as is, otherwise next page never advances
+    let tail_off = if eof {
+        chars_end
+    } else {
+        match strs.pop() {
+            None => 0,
+            Some(inner) if strs.is_empty() => {
+                strs.push(inner);
+                chars_end
+            }
+            Some(inner) => inner.0,
+        }
+    };
+    let mut retval = 0;
+    let mut idx = 0;
+    let mut lin: String = " ".to_owned();
+    let mut height = 0;
+    let mut lin_width = 1;
+    let dlwidth = text_width(con);
+    let mut lstart = con.def.page_at_lstart;
+    let mut nl_before = con.def.page_nl;
+    let mut lin_off: Option<usize> = None; //note offset of first char of display line
+    macro_rules! dline_gutter {
+        ($off: expr) => {
+            gutter(
+                con,
+                lstart,
+                nl_before,
+                con.def.bookmark + lin_off.unwrap_or($off) as u64,
+            )
+        };
+    }
+    loop {
+        let (off, cstr) = match strs.get(idx) {
+            None => {
+                if " " != lin {
+                    cout_dline(con, &(dline_gutter!(tail_off) + &lin));
+                    retval = tail_off;
+                }
+                if eof {
+                    cout_dline(con, "END-OF-FILE");
+                }
+                break;
+            }
+            Some(inner) => (inner.0, inner.1.as_str()),
+        };
+        if cstr == "\n" {
+            idx += 1;
+            if " " != lin {
+                cout_dline(con, &(dline_gutter!(off) + &lin));
+                retval = off;
+                height += 1;
+            }
+            lin = "$".to_owned();
+            lin_width = 1;
+            lin_off = None;
+            lstart = true;
+            nl_before = nl_before.map(|cnt| cnt + 1);
+            if height == con.def_dheight {
+                break;
+            }
+            continue;
+        }
+        let cell_width = if cstr == "\t" {
+            tab_width(con, lin_width)
+        } else {
+            disp_width(con, cstr)
+        };
+        //note a cluster wider than the whole display line is still pushed to avoid looping forever
+        if lin_width > 1 && lin_width + cell_width > dlwidth {
+            cout_dline(con, &(dline_gutter!(off) + &lin));
+            retval = off;
+            height += 1;
+            lin = " ".to_owned();
+            lin_width = 1;
+            lin_off = None;
+            lstart = false;
+            if height == con.def_dheight {
+                break;
+            }
+            continue;
+        }
+        let hl_off = con.def.bookmark + off as u64;
+        if cstr == "\t" {
+            push_hl(con, &mut lin, hl_off, &" ".repeat(cell_width));
+        } else {
+            push_hl(con, &mut lin, hl_off, cstr);
+        }
+        lin_width += cell_width;
+        lin_off.get_or_insert(off);
+        idx += 1;
+    }
+    retval
+}
+
+//note all display lines and END-OF-FILE go through this, so a page can be rendered without output to locate display line boundaries
+fn cout_dline(con: &Ctx, lin: &str) {
+    if con.def.muted.get() {
+        return;
+    }
+    match &con.def.dline_sink {
+        None => {
+            coutln!(lin);
+        }
+        Some(sink) => {
+            sink.borrow_mut().push(lin.to_owned());
+        }
+    }
+}
+
+fn esc_bytes(blob: &[u8]) -> String {
+    blob.iter().map(|byt| format!("\\x{:02X}", byt)).collect()
+}
+
+//note East Asian Width based, zero-width joiners and combining marks take no column
+fn disp_width(con: &Ctx, cstr: &str) -> usize {
+    use unicode_width::UnicodeWidthStr;
+    if con.def.ambiwidth_double {
+        cstr.width_cjk()
+    } else {
+        cstr.width()
+    }
+}
+
+//note returns None if the byte should be pushed to terminal as is
+fn ctrl_repr(con: &Ctx, byt: u8) -> Option<String> {
+    //note \n is line ending and \t is not treated as dangerous control character
+    if !byt.is_ascii_control() || byt == b'\n' || byt == b'\t' {
+        return None;
+    }
+    match con.def.ctrl_policy {
+        CtrlPolicy::Caret => Some(format!("^{}", (byt ^ 0x40) as char)),
+        CtrlPolicy::Hex => Some(esc_bytes(&[byt])),
+        CtrlPolicy::Glyph => {
+            let pic = if byt == 0x7F {
+                0x2421
+            } else {
+                0x2400 + byt as u32
+            };
+            Some(char::from_u32(pic).unwrap().into())
+        }
+        CtrlPolicy::Raw => None,
+    }
+}
+
+//note returns None if the invalid bytes should be handled the legacy way (skipped or decoded lossily)
+fn invalid_repr(con: &Ctx, blob: &[u8]) -> Option<String> {
+    if con.def.view_mode == ViewMode::Mixed {
+        return Some(esc_bytes(blob));
+    }
+    match con.def.ctrl_policy {
+        CtrlPolicy::Hex => Some(esc_bytes(blob)),
+        CtrlPolicy::Caret | CtrlPolicy::Glyph => Some("\u{FFFD}".into()),
+        CtrlPolicy::Raw => None,
+    }
+}
+
+fn hex_offset_width(con: &Ctx) -> usize {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    cmp::max(8, format!("{:x}", file_len).len())
+}
+
+fn hex_row_len(con: &Ctx) -> usize {
+    //note each byte takes 3 columns in hex part and 1 column in ASCII part, plus 3 columns of separators after offset
+    let row_len = (con.def_dlwidth.saturating_sub(hex_offset_width(con) + 3)) / 4;
+    if row_len >= 4 {
+        row_len - row_len % 4
+    } else {
+        cmp::max(1, row_len)
+    }
+}
+
+fn render_hex(buf: &[u8], rlen: usize, con: &Ctx) -> usize {
+    let cont = &buf[0..rlen];
+    let offw = hex_offset_width(con);
+    let row_len = hex_row_len(con);
+    let mut off = 0;
+    for _ in 0..con.def_dheight {
+        if off >= cont.len() {
+            break;
+        }
+        let row = &cont[off..cmp::min(off + row_len, cont.len())];
+        let mut lin = format!("{:0offw$x} ", con.def.bookmark + off as u64, offw = offw);
+        for byt in row {
+            lin.push_str(&format!(" {:02x}", byt));
+        }
+        for _ in row.len()..row_len {
+            lin.push_str("   ");
+        }
+        lin.push_str("  ");
+        lin.extend(row.iter().map(|byt| match byt {
+            0x20..=0x7E => *byt as char,
+            _ => '.',
+        }));
+        cout_dline(con, &lin);
+        off += row.len();
+    }
+    if rlen < buf.len() && off == cont.len() {
+        cout_dline(con, "END-OF-FILE");
+    }
+    off
+}
+
+fn bytecount_nl(blob: &[u8]) -> u64 {
+    blob.iter().filter(|byt| **byt == b'\n').count() as u64
+}
+
+//note safe for GB18030 as well because \n never appears as a trailing byte
+fn count_nl_in(fil: &mut fs::File, from: u64, to: u64) -> CustRes<u64> {
+    use std::io::*;
+    fil.seek(io::SeekFrom::Start(from))?;
+    let mut rdr = fil.take(to - from);
+    let mut buf = vec![0; 0x10000];
+    let mut retval = 0;
+    loop {
+        let rlen = rdr.read(&mut buf)?;
+        if rlen == 0 {
+            break;
+        }
+        retval += bytecount_nl(&buf[..rlen]);
+    }
+    Ok(retval)
+}
+
+//note returns count of newlines before the offset, counted from nearest known anchor, None if it is too far away from any anchor
+fn nl_count_before(con: &mut Ctx, off: u64) -> CustRes<Option<u64>> {
+    if off <= con.def.bom_end {
+        return Ok(Some(0));
+    }
+    let below = match con.def.line_anchors.range(..=off).next_back() {
+        None => (con.def.bom_end, 0),
+        Some((anchor, cnt)) => (*anchor, *cnt),
+    };
+    let above = con
+        .def
+        .line_anchors
+        .range(off..)
+        .next()
+        .map(|(anchor, cnt)| (*anchor, *cnt));
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let retval = match above {
+        Some((anchor, cnt)) if anchor - off < off - below.0 => {
+            if anchor - off > LINE_COUNT_SCAN_LIMIT {
+                return Ok(None);
+            }
+            cnt.checked_sub(count_nl_in(fil, off, anchor)?)
+        }
+        _ => {
+            if off - below.0 > LINE_COUNT_SCAN_LIMIT {
+                return Ok(None);
+            }
+            Some(below.1 + count_nl_in(fil, below.0, off)?)
+        }
+    };
+    if let Some(cnt) = retval {
+        con.def.line_anchors.insert(off, cnt);
+    }
+    Ok(retval)
+}
+
+fn at_line_start(con: &mut Ctx, off: u64) -> CustRes<bool> {
+    use std::io::*;
+    if off <= con.def.bom_end {
+        return Ok(true);
+    }
+    let fil = con.def.fsfile.as_mut().unwrap();
+    fil.seek(io::SeekFrom::Start(off - 1))?;
+    let mut byt = [0u8];
+    fil.read_exact(&mut byt)?;
+    Ok(byt[0] == b'\n')
+}
+
+fn lno_width(con: &Ctx) -> usize {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    (file_len + 1).to_string().len()
+}
+
+fn dline_off_width(con: &Ctx) -> usize {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    match con.def.offsets {
+        None => 0,
+        Some(OffsetRadix::Dec) => file_len.to_string().len(),
+        Some(OffsetRadix::Hex) => hex_offset_width(con),
+    }
+}
+
+fn gutter_width(con: &Ctx) -> usize {
+    let mut retval = 0;
+    if con.def.nu {
+        retval += lno_width(con) + 1;
+    }
+    if con.def.offsets.is_some() {
+        retval += dline_off_width(con) + 1;
+    }
+    retval
+}
+
+//note display line width minus gutter
+fn text_width(con: &Ctx) -> usize {
+    cmp::max(
+        MIN_TEXT_WIDTH,
+        con.def_dlwidth.saturating_sub(gutter_width(con)),
+    )
+}
+
+//note line number is only shown on display line starting a real line, ? if line number is unknown
+//note offset (of first char of display line) is shown on every display line
+fn gutter(con: &Ctx, lstart: bool, nl_before: Option<u64>, dline_off: u64) -> String {
+    let mut retval = String::new();
+    if con.def.nu {
+        let width = lno_width(con);
+        retval += &match (lstart, nl_before) {
+            (false, _) => " ".repeat(width + 1),
+            (true, None) => format!("{:>width$} ", "?", width = width),
+            (true, Some(cnt)) => format!("{:>width$} ", cnt + 1, width = width),
+        };
+    }
+    let width = dline_off_width(con);
+    match con.def.offsets {
+        None => {}
+        Some(OffsetRadix::Dec) => {
+            retval += &format!("{:>width$} ", dline_off, width = width);
+        }
+        Some(OffsetRadix::Hex) => {
+            retval += &format!("{:0width$x} ", dline_off, width = width);
+        }
     }
-    coutln!(lin);
     retval
 }
 
@@ -869,92 +1988,2907 @@ fn show_page(con: &mut Ctx) -> CustRes<()> {
     //optimize the logic of checking whether enough bytes are read can be incremental instead of re-calculating every time
     use std::io::*;
     //use io::Seek;
+    if con.def.filter.is_some() && con.def.view_mode != ViewMode::Hex {
+        return show_filter_page(con);
+    }
+    if con.def.csv.is_some() && con.def.view_mode != ViewMode::Hex {
+        return show_csv_page(con);
+    }
     let bufsize = big_enough_buf_size(con);
     let tr = con.tr.clone();
-    if con.def.bookmark < con.def.bom_end {
+    //note hex dump shows BOM as well
+    if con.def.view_mode != ViewMode::Hex && con.def.bookmark < con.def.bom_end {
         con.def.bookmark = con.def.bom_end;
     }
-    let fil = con.def.fsfile.as_mut().unwrap();
-    fil.seek(io::SeekFrom::Start(con.def.bookmark))?;
-    let mut buf = vec![0; bufsize];
-    let rlen = read_to_buf(fil, &mut buf)?;
-    let used_len = tr.render(&buf, rlen, con);
-    con.def.bookmark_end = con.def.bookmark + used_len as u64;
+    if con.def.nu {
+        con.def.page_nl = nl_count_before(con, con.def.bookmark)?;
+        con.def.page_at_lstart = at_line_start(con, con.def.bookmark)?;
+    }
+    let fil = con.def.fsfile.as_mut().unwrap();
+    fil.seek(io::SeekFrom::Start(con.def.bookmark))?;
+    let mut buf = vec![0; bufsize];
+    let rlen = read_to_buf(fil, &mut buf)?;
+    let base = con.def.bookmark;
+    find_hits(con, &buf[..rlen], base);
+    find_colors(con, &buf[..rlen], base);
+    let used_len = if con.def.view_mode == ViewMode::Hex {
+        render_hex(&buf, rlen, con)
+    } else if con.def.grapheme {
+        let mut strs = tr.buf2strs(&buf, rlen, con, true);
+        strs.retain(|tup| !tup.1.is_empty());
+        let eof = rlen < buf.len();
+        //note a char cut by the end of buffer is not in strs
+        let chars_end = match (eof, strs.last()) {
+            (false, Some(tup)) => tup.0 + tr.chr_len(&buf[..rlen], tup.0),
+            _ => rlen,
+        };
+        render_g(con, group_graphemes(strs), chars_end, eof)
+    } else {
+        tr.render(&buf, rlen, con)
+    };
+    con.def.bookmark_end = con.def.bookmark + used_len as u64;
+    if let (true, Some(cnt)) = (con.def.nu, con.def.page_nl) {
+        let nl_before = cnt + bytecount_nl(&buf[..used_len]);
+        con.def.line_anchors.insert(con.def.bookmark_end, nl_before);
+    }
+    cout_status_if_needed(con);
+    Ok(())
+}
+
+fn percentage_wise(con: &mut Ctx) -> CustRes<()> {
+    let digits = subsli_cut_rear!(con.def.iline, 1);
+    let perc = match digits.parse::<f64>() {
+        Err(_) => {
+            coutln!("Percentage invalid input.");
+            return Ok(());
+        }
+        Ok(inner) => inner / 100.0,
+    };
+    let chk_floating_point = perc >= 0f64 && perc <= 1.0; //note this handles NaN etc.
+    if !chk_floating_point {
+        coutln!("Percentage invalid number.");
+        return Ok(());
+    }
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    //"Casting from an integer to float will produce the closest possible float"
+    //"Casting from a float to an integer will round the float towards zero"
+    let mut bm = (file_len as f64 * perc) as u64;
+    if bm > file_len {
+        bm = file_len;
+    }
+    con.def.bookmark = bm;
+    con.def.bookmark_end = bm;
+    show_page(con)?;
+    Ok(())
+}
+
+fn cmd_g(con: &Ctx) {
+    coutln!(con.def.text_file_path_str);
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let perc = con.def.bookmark * 100 / file_len;
+    println!("{}{}", perc, "%");
+    println!("{}{}{}", con.def.bookmark, "/", file_len);
+}
+
+//note compact summary of current page, never wider than display line
+fn status_line(con: &Ctx) -> String {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let perc = (con.def.bookmark * 100)
+        .checked_div(file_len)
+        .unwrap_or(100);
+    let tail = format!(
+        " {}% {}-{}/{} {}{}{}",
+        perc,
+        con.def.bookmark,
+        con.def.bookmark_end,
+        file_len,
+        con.enc.name(),
+        if con.def.filter.is_some() {
+            " FILTERED"
+        } else {
+            ""
+        },
+        if con.def.bookmark_end >= file_len {
+            " EOF"
+        } else {
+            ""
+        }
+    );
+    let room = con.def_dlwidth.saturating_sub(tail.len());
+    let path = &con.def.text_file_path_str;
+    let fnm = match con.def.text_file_path.file_name() {
+        None => path.clone(),
+        Some(inner) => inner.to_string_lossy().into_owned(),
+    };
+    let head = if disp_width(con, path) <= room {
+        path.clone()
+    } else {
+        fnm
+    };
+    let mut retval = String::new();
+    let mut width = 0;
+    for chr in head.chars().chain(tail.chars()) {
+        let chr_width = disp_width(con, chr.encode_utf8(&mut [0; 4]));
+        if width + chr_width > con.def_dlwidth {
+            break;
+        }
+        retval.push(chr);
+        width += chr_width;
+    }
+    retval
+}
+
+//note full-screen mode has its own status bar
+fn cout_status_if_needed(con: &Ctx) {
+    if con.def.status && !con.def.in_tui {
+        cout_dline(con, &status_line(con));
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum TuiKey {
+    PgDn,
+    PgUp,
+    Down,
+    Up,
+    Home,
+    End,
+    Chr(u8),
+    Esc,
+    Other,
+}
+
+//note restores terminal when dropped, even during panic
+#[cfg(unix)]
+struct TuiScreen {
+    saved: libc::termios,
+}
+#[cfg(unix)]
+impl TuiScreen {
+    fn enter() -> Option<Self> {
+        let mut saved: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+            return None;
+        }
+        let retval = Self { saved };
+        retval.raw();
+        cout_n_flush!("\x1b[?1049h\x1b[?25l");
+        Some(retval)
+    }
+    //note OPOST is kept so display lines can still be printed with \n
+    fn raw(&self) {
+        let mut tio = self.saved;
+        tio.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        tio.c_iflag &= !(libc::IXON | libc::ICRNL);
+        tio.c_cc[libc::VMIN] = 1;
+        tio.c_cc[libc::VTIME] = 0;
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &tio);
+        }
+    }
+    fn cooked(&self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.saved);
+        }
+    }
+}
+#[cfg(unix)]
+impl Drop for TuiScreen {
+    fn drop(&mut self) {
+        self.cooked();
+        cout_n_flush!("\x1b[?25h\x1b[?1049l");
+    }
+}
+
+const TUI_KEY_SEQS: &[(&[u8], TuiKey)] = &[
+    (b"\x1b[6~", TuiKey::PgDn),
+    (b"\x1b[5~", TuiKey::PgUp),
+    (b"\x1b[B", TuiKey::Down),
+    (b"\x1bOB", TuiKey::Down),
+    (b"\x1b[A", TuiKey::Up),
+    (b"\x1bOA", TuiKey::Up),
+    (b"\x1b[H", TuiKey::Home),
+    (b"\x1bOH", TuiKey::Home),
+    (b"\x1b[1~", TuiKey::Home),
+    (b"\x1b[7~", TuiKey::Home),
+    (b"\x1b[F", TuiKey::End),
+    (b"\x1bOF", TuiKey::End),
+    (b"\x1b[4~", TuiKey::End),
+    (b"\x1b[8~", TuiKey::End),
+];
+
+//note takes one key off the front of pending bytes, None if more bytes are needed to tell
+fn tui_parse_key(pending: &mut Vec<u8>) -> Option<TuiKey> {
+    let first = *pending.first()?;
+    if first != 0x1b {
+        pending.remove(0);
+        return Some(TuiKey::Chr(first));
+    }
+    let len = match pending.get(1) {
+        None => {
+            return None;
+        }
+        //note CSI sequence ends with a byte in 0x40..=0x7E
+        Some(b'[') => {
+            3 + pending[2..]
+                .iter()
+                .position(|byt| (0x40..=0x7E).contains(byt))?
+        }
+        Some(b'O') => {
+            if pending.len() < 3 {
+                return None;
+            }
+            3
+        }
+        Some(_) => {
+            pending.remove(0);
+            return Some(TuiKey::Esc);
+        }
+    };
+    let key = TUI_KEY_SEQS
+        .iter()
+        .find(|tup| tup.0 == &pending[..len])
+        .map_or(TuiKey::Other, |tup| tup.1);
+    pending.drain(..len);
+    Some(key)
+}
+
+//note returns None if no key is pressed within a short time, so that terminal resizing can be handled
+//note one read might carry several keys (e.g. key repeat or paste), the rest is kept in pending for next call
+#[cfg(unix)]
+fn tui_read_key(pending: &mut Vec<u8>) -> CustRes<Option<TuiKey>> {
+    loop {
+        if let Some(key) = tui_parse_key(pending) {
+            return Ok(Some(key));
+        }
+        let mut pfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pfd, 1, 100) } <= 0 {
+            if pending.is_empty() {
+                return Ok(None);
+            }
+            //note incomplete sequence (e.g. Esc pressed alone) is not waited for any longer
+            let lone_esc = pending == b"\x1b";
+            pending.clear();
+            return Ok(Some(if lone_esc { TuiKey::Esc } else { TuiKey::Other }));
+        }
+        let mut buf = [0u8; 64];
+        let rlen = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        if rlen <= 0 {
+            return Err("Failed to read key from stdin".into());
+        }
+        pending.extend_from_slice(&buf[..rlen as usize]);
+    }
+}
+
+//note unlike line-oriented mode, the whole terminal is used regardless of env vars
+fn tui_fit(con: &mut Ctx) {
+    if let Some((cols, rows)) = term_size() {
+        con.def_dlwidth = cmp::max(MIN_DISPLAY_LINE_WIDTH, cols);
+        con.def_dheight = cmp::max(MIN_DISPLAY_HEIGHT, rows.saturating_sub(TERM_RESERVED_ROWS));
+    }
+}
+
+fn tui_draw(con: &mut Ctx) -> CustRes<()> {
+    cout_n_flush!("\x1b[H\x1b[2J");
+    con.def.bookmark_end = con.def.bookmark;
+    show_page(con)?;
+    let status = status_line(con);
+    let padding = " ".repeat(con.def_dlwidth - disp_width(con, &status));
+    cout_n_flush!(format!(
+        "\x1b[{};1H\x1b[7m{}{}\x1b[0m",
+        con.def_dheight + TERM_RESERVED_ROWS,
+        status,
+        padding
+    ));
+    Ok(())
+}
+
+//note renders without output just to find where the page starts
+fn locate_prev_page(con: &mut Ctx, dheight: usize) -> CustRes<()> {
+    let full_dheight = con.def_dheight;
+    con.def_dheight = dheight;
+    let muted = con.def.muted.replace(true);
+    con.def.bookmark_end = con.def.bookmark;
+    let res = show_prev_page(con);
+    con.def.muted.set(muted);
+    con.def_dheight = full_dheight;
+    res
+}
+
+//note renders without output just to find where the next display line starts
+fn locate_next_dline(con: &mut Ctx) -> CustRes<()> {
+    let full_dheight = con.def_dheight;
+    con.def_dheight = 1;
+    let muted = con.def.muted.replace(true);
+    con.def.bookmark_end = con.def.bookmark;
+    let res = show_page(con);
+    con.def.muted.set(muted);
+    con.def_dheight = full_dheight;
+    res?;
+    con.def.bookmark = con.def.bookmark_end;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn tui(con: &mut Ctx) -> CustRes<bool> {
+    if con.def.in_tui {
+        coutln!("Already in full-screen mode.");
+        return Ok(true);
+    }
+    let is_tty =
+        unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 };
+    if !is_tty || env::var("TERM").map_or(true, |term| term == "dumb") {
+        coutln!("Full-screen mode needs a capable terminal.");
+        return Ok(true);
+    }
+    let screen = match TuiScreen::enter() {
+        None => {
+            coutln!("Failed to switch terminal to raw mode.");
+            return Ok(true);
+        }
+        Some(inner) => inner,
+    };
+    let (dlwidth, dheight) = (con.def_dlwidth, con.def_dheight);
+    con.def.in_tui = true;
+    let retval = tui_loop(con, &screen);
+    con.def.in_tui = false;
+    con.def_dlwidth = dlwidth;
+    con.def_dheight = dheight;
+    drop(screen);
+    if retval.is_ok() {
+        con.def.bookmark_end = con.def.bookmark;
+        show_page(con)?;
+    }
+    retval
+}
+#[cfg(not(unix))]
+fn tui(_con: &mut Ctx) -> CustRes<bool> {
+    coutln!("Full-screen mode is not supported on this platform.");
+    Ok(true)
+}
+
+//note line editing for `:`, `/` and `?` takes keys from the same raw input as other keys, Esc or Ctrl-C cancels
+#[cfg(unix)]
+fn tui_read_line(con: &Ctx, pending: &mut Vec<u8>, prefix: u8) -> CustRes<Option<String>> {
+    let mut line: Vec<u8> = vec![];
+    cout_n_flush!("\x1b[?25h");
+    defer! {
+        cout_n_flush!("\x1b[?25l");
+    }
+    loop {
+        cout_n_flush!(format!(
+            "\x1b[{};1H\x1b[2K{}{}",
+            con.def_dheight + TERM_RESERVED_ROWS,
+            prefix as char,
+            String::from_utf8_lossy(&line)
+        ));
+        let key = match tui_read_key(pending)? {
+            None => {
+                continue;
+            }
+            Some(inner) => inner,
+        };
+        match key {
+            TuiKey::Chr(b'\r' | b'\n') => {
+                return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+            }
+            TuiKey::Esc | TuiKey::Chr(3) => {
+                return Ok(None);
+            }
+            TuiKey::Chr(0x7F | 0x08) => {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                //note removes a whole UTF-8 char
+                while let Some(byt) = line.pop() {
+                    if byt & 0b11000000 != 0b10000000 {
+                        break;
+                    }
+                }
+            }
+            TuiKey::Chr(byt) if byt >= 0x20 => {
+                line.push(byt);
+            }
+            _ => {}
+        }
+    }
+}
+
+//note stdout and stderr go to an unlinked temp file while a command runs in full-screen mode, so its output can be shown after redrawing instead of being wiped
+#[cfg(unix)]
+struct OutCapture {
+    fil: fs::File,
+    saved: Option<[libc::c_int; 2]>,
+}
+#[cfg(unix)]
+impl OutCapture {
+    const FDS: [libc::c_int; 2] = [libc::STDOUT_FILENO, libc::STDERR_FILENO];
+    fn begin() -> CustRes<Self> {
+        use std::io::Write;
+        use std::os::fd::AsRawFd;
+        let path = env::temp_dir().join(format!("{}_{}.out", PKG_NAME, process::id()));
+        let fil = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        fs::remove_file(&path)?;
+        io::stdout().flush()?;
+        io::stderr().flush()?;
+        let saved = Self::FDS.map(|fd| unsafe {
+            let dupd = libc::dup(fd);
+            libc::dup2(fil.as_raw_fd(), fd);
+            dupd
+        });
+        Ok(Self {
+            fil,
+            saved: Some(saved),
+        })
+    }
+    fn restore(&mut self) {
+        use std::io::Write;
+        if let Some(saved) = self.saved.take() {
+            let _ = io::stdout().flush();
+            let _ = io::stderr().flush();
+            for (fd, dupd) in Self::FDS.into_iter().zip(saved) {
+                unsafe {
+                    libc::dup2(dupd, fd);
+                    libc::close(dupd);
+                }
+            }
+        }
+    }
+    fn end(&mut self) -> CustRes<String> {
+        use std::io::*;
+        self.restore();
+        self.fil.seek(io::SeekFrom::Start(0))?;
+        let mut buf = vec![];
+        self.fil.read_to_end(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+#[cfg(unix)]
+impl Drop for OutCapture {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+//note one line goes to status row, more lines take the screen until a key is pressed
+#[cfg(unix)]
+fn tui_show_output(con: &mut Ctx, out: &str, pending: &mut Vec<u8>) -> CustRes<()> {
+    let lines: Vec<&str> = out.lines().filter(|lin| !lin.trim().is_empty()).collect();
+    match lines.as_slice() {
+        [] => {}
+        [lin] => {
+            let mut msg = String::new();
+            let mut width = 0;
+            for chr in strip_sgr(lin).chars() {
+                width += disp_width(con, chr.encode_utf8(&mut [0; 4]));
+                if width > con.def_dlwidth {
+                    break;
+                }
+                msg.push(chr);
+            }
+            cout_n_flush!(format!(
+                "\x1b[{};1H\x1b[2K{}",
+                con.def_dheight + TERM_RESERVED_ROWS,
+                msg
+            ));
+        }
+        _ => {
+            cout_n_flush!("\x1b[H\x1b[2J");
+            for lin in lines {
+                coutln!(lin);
+            }
+            cout_n_flush!("\x1b[7mPress any key to continue\x1b[0m");
+            while tui_read_key(pending)?.is_none() {}
+            tui_draw(con)?;
+        }
+    }
+    Ok(())
+}
+
+//note removes color codes (e.g. from log lines) so that display width can be told
+#[cfg(unix)]
+fn strip_sgr(lin: &str) -> String {
+    let mut retval = String::new();
+    let mut rest = lin;
+    while let Some(idx) = rest.find("\x1b[") {
+        retval.push_str(&rest[..idx]);
+        rest = &rest[idx + 2..];
+        rest = match rest.find(|chr: char| chr.is_ascii_alphabetic()) {
+            None => "",
+            Some(end) => &rest[end + 1..],
+        };
+    }
+    retval.push_str(rest);
+    retval
+}
+
+#[cfg(unix)]
+fn tui_loop(con: &mut Ctx, screen: &TuiScreen) -> CustRes<bool> {
+    tui_fit(con);
+    tui_draw(con)?;
+    let mut pending = vec![];
+    loop {
+        let key = match tui_read_key(&mut pending)? {
+            None => {
+                if WINCH.swap(false, sync::atomic::Ordering::Relaxed) {
+                    tui_fit(con);
+                    tui_draw(con)?;
+                }
+                continue;
+            }
+            Some(inner) => inner,
+        };
+        match key {
+            TuiKey::PgDn | TuiKey::Chr(b' ') | TuiKey::Chr(b'f') | TuiKey::Chr(b'\r') => {
+                con.def.bookmark = con.def.bookmark_end;
+            }
+            TuiKey::PgUp | TuiKey::Chr(b'b') => {
+                locate_prev_page(con, con.def_dheight)?;
+            }
+            TuiKey::Down | TuiKey::Chr(b'j') => {
+                locate_next_dline(con)?;
+            }
+            TuiKey::Up | TuiKey::Chr(b'k') => {
+                locate_prev_page(con, 1)?;
+            }
+            TuiKey::Home | TuiKey::Chr(b'g') => {
+                con.def.bookmark = 0; //note show_page moves it to bom_end if needed
+            }
+            TuiKey::End | TuiKey::Chr(b'G') => {
+                con.def.bookmark = con.def.fsmd.as_ref().unwrap().len();
+                locate_prev_page(con, con.def_dheight)?;
+            }
+            TuiKey::Chr(b'q') | TuiKey::Chr(3) => {
+                return Ok(true);
+            }
+            TuiKey::Chr(prefix @ (b':' | b'/' | b'?')) => {
+                let linestr = match tui_read_line(con, &mut pending, prefix)? {
+                    None => {
+                        tui_draw(con)?;
+                        continue;
+                    }
+                    Some(inner) => inner,
+                };
+                con.def.iline = if prefix == b':' {
+                    linestr
+                } else {
+                    format!("{}{}", prefix as char, linestr)
+                };
+                //note command runs in cooked mode so that Ctrl-C can cancel a long search
+                //note pages shown by the command are not printed, the page is redrawn afterwards
+                screen.cooked();
+                let mut capture = OutCapture::begin()?;
+                con.def.muted.set(true);
+                let res = run_cmd(con);
+                con.def.muted.set(false);
+                let out = capture.end()?;
+                screen.raw();
+                if !res? {
+                    return Ok(false);
+                }
+                tui_draw(con)?;
+                tui_show_output(con, &out, &mut pending)?;
+                continue;
+            }
+            _ => {
+                continue;
+            }
+        }
+        tui_draw(con)?;
+    }
+}
+
+fn cmd_se(con: &mut Ctx) -> CustRes<()> {
+    let opt = match con.def.iline.split_once(' ') {
+        None => "".to_owned(),
+        Some((_, inner)) => inner.trim().to_owned(),
+    };
+    let (name, val) = match opt.split_once('=') {
+        None => (opt.as_str(), ""),
+        Some(inner) => inner,
+    };
+    match name {
+        "hex" => {
+            con.def.view_mode = ViewMode::Hex;
+        }
+        "mixed" => {
+            con.def.view_mode = ViewMode::Mixed;
+        }
+        "text" => {
+            con.def.view_mode = ViewMode::Text;
+        }
+        "ambiwidth" => {
+            con.def.ambiwidth_double = match val {
+                "single" => false,
+                "double" => true,
+                _ => {
+                    coutln!("Option value not recognized.");
+                    return Ok(());
+                }
+            };
+        }
+        "nu" | "number" => {
+            con.def.nu = true;
+        }
+        "nonu" | "nonumber" => {
+            con.def.nu = false;
+        }
+        "ws" | "wrapscan" => {
+            con.def.wrapscan = true;
+        }
+        "nows" | "nowrapscan" => {
+            con.def.wrapscan = false;
+        }
+        "ic" | "ignorecase" => {
+            con.def.ignorecase = true;
+        }
+        "noic" | "noignorecase" => {
+            con.def.ignorecase = false;
+        }
+        "scs" | "smartcase" => {
+            con.def.smartcase = true;
+        }
+        "noscs" | "nosmartcase" => {
+            con.def.smartcase = false;
+        }
+        "wholeword" => {
+            con.def.wholeword = true;
+        }
+        "nowholeword" => {
+            con.def.wholeword = false;
+        }
+        "hl" => {
+            con.def.hl = true;
+        }
+        "nohl" => {
+            con.def.hl = false;
+        }
+        "loghl" => {
+            load_log_rules(con)?;
+            con.def.loghl = true;
+        }
+        "nologhl" => {
+            con.def.loghl = false;
+        }
+        "jsonpp" => {
+            con.def.json_pp = true;
+        }
+        "nojsonpp" => {
+            con.def.json_pp = false;
+        }
+        "offsets" => {
+            con.def.offsets = match val {
+                "" | "dec" => Some(OffsetRadix::Dec),
+                "hex" => Some(OffsetRadix::Hex),
+                _ => {
+                    coutln!("Option value not recognized.");
+                    return Ok(());
+                }
+            };
+        }
+        "nooffsets" => {
+            con.def.offsets = None;
+        }
+        "status" => {
+            con.def.status = true;
+            fit_term_size(con);
+        }
+        "nostatus" => {
+            con.def.status = false;
+            fit_term_size(con);
+        }
+        "csv" | "tsv" => {
+            con.def.csv = match (name, val) {
+                ("tsv", _) | (_, "tab") => Some(b'\t'),
+                (_, "") => Some(b','),
+                (_, delim) if delim.len() == 1 && delim != "\"" => Some(delim.as_bytes()[0]),
+                _ => {
+                    coutln!("Option value not recognized.");
+                    return Ok(());
+                }
+            };
+            con.def.csv_header = None;
+            con.def.csv_hint = None;
+        }
+        "nocsv" => {
+            con.def.csv = None;
+        }
+        "bsfield" => {
+            con.def.bs_field = match val.parse::<usize>() {
+                Ok(inner) => inner,
+                _ => {
+                    coutln!("Option value invalid.");
+                    return Ok(());
+                }
+            };
+        }
+        "bsdelim" => {
+            con.def.bs_delim = match val {
+                "tab" => b'\t',
+                _ if val.len() == 1 => val.as_bytes()[0],
+                _ => {
+                    coutln!("Option value not recognized.");
+                    return Ok(());
+                }
+            };
+        }
+        "pplimit" => {
+            con.def.pp_limit = match val.parse::<usize>() {
+                Ok(inner) if inner >= MIN_PP_LIMIT => inner,
+                _ => {
+                    coutln!("Option value invalid.");
+                    return Ok(());
+                }
+            };
+        }
+        "ts" | "tabstop" => {
+            con.def.tabstop = match val.parse::<usize>() {
+                Ok(inner) if (MIN_TABSTOP..=MAX_TABSTOP).contains(&inner) => inner,
+                _ => {
+                    coutln!("Option value invalid.");
+                    return Ok(());
+                }
+            };
+        }
+        "grapheme" => {
+            con.def.grapheme = true;
+        }
+        "nographeme" => {
+            con.def.grapheme = false;
+        }
+        "ctrl" => {
+            con.def.ctrl_policy = match val {
+                "caret" => CtrlPolicy::Caret,
+                "hex" => CtrlPolicy::Hex,
+                "glyph" => CtrlPolicy::Glyph,
+                "raw" => CtrlPolicy::Raw,
+                _ => {
+                    coutln!("Option value not recognized.");
+                    return Ok(());
+                }
+            };
+        }
+        _ => {
+            coutln!("Option not recognized.");
+            return Ok(());
+        }
+    }
+    if con.def.fsmd.is_some() {
+        con.def.bookmark_end = con.def.bookmark;
+        show_page(con)?;
+    }
+    Ok(())
+}
+
+//note returns None if there is nothing to search
+//note prefix_len is the length of command before pattern (e.g. 1 for slash)
+fn search_pat_of_iline(con: &Ctx, prefix_len: usize) -> Option<SearchPat> {
+    debug_assert!(con.def.iline.len() >= prefix_len);
+    if con.def.iline.len() == prefix_len {
+        info!("{}", "Cannot search without input");
+        return None;
+    }
+    let ptn = &con.def.iline[prefix_len..];
+    let (blob, _enc, res) = con.enc.encode(ptn);
+    if res {
+        info!("{}", "Unmappable characters in input");
+        return None;
+    }
+    //note like vim, smartcase only takes effect when ignorecase is on
+    let icase = con.def.ignorecase && !(con.def.smartcase && ptn.chars().any(char::is_uppercase));
+    let mut fold_mask = vec![];
+    let mut folded = vec![];
+    if icase {
+        //note only ASCII chars are folded byte by byte, trailing bytes of multi-byte chars (e.g. in GB18030) are compared exactly
+        for chr in ptn.chars() {
+            let clen = con.enc.encode(chr.encode_utf8(&mut [0; 4])).0.len();
+            fold_mask.extend(iter::repeat_n(chr.is_ascii(), clen));
+        }
+        let cased_non_ascii = ptn
+            .chars()
+            .any(|chr| !chr.is_ascii() && (chr.is_lowercase() || chr.is_uppercase()));
+        if con.enc == encoding_rs::UTF_8 && cased_non_ascii {
+            folded = ptn.chars().map(fold_chr).collect();
+        }
+    }
+    let retval = SearchPat {
+        blob: blob.into_owned(),
+        fold_mask,
+        folded,
+        word: con.def.wholeword,
+        utf8: con.enc == encoding_rs::UTF_8,
+    };
+    Some(retval)
+}
+
+fn search_bytes(con: &mut Ctx) -> CustRes<()> {
+    let pat = match search_pat_of_iline(con, 1) {
+        None => {
+            return Ok(());
+        }
+        Some(inner) => inner,
+    };
+    //note filter (&) and count patterns do not become the last search pattern
+    con.def.search_pat = pat.clone();
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let from = con.def.bookmark;
+    let bom_end = con.def.bom_end;
+    let total = if con.def.wrapscan {
+        file_len - bom_end
+    } else {
+        file_len.saturating_sub(from)
+    };
+    let mut prog = SearchProgress::new(total);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let mut found = scan(con, &pat, from, file_len, false, &mut prog)?;
+    if found.is_none() && con.def.wrapscan && !INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        found = scan(con, &pat, bom_end, from, false, &mut prog)?;
+        if found.is_some() {
+            coutln!("Search hit BOTTOM, continuing at TOP.");
+        }
+    }
+    goto_search_hit(con, found)
+}
+
+fn search_bytes_rev(con: &mut Ctx) -> CustRes<()> {
+    let pat = match search_pat_of_iline(con, 1) {
+        None => {
+            return Ok(());
+        }
+        Some(inner) => inner,
+    };
+    con.def.search_pat = pat.clone();
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let from = cmp::min(con.def.bookmark, file_len);
+    let bom_end = con.def.bom_end;
+    let total = if con.def.wrapscan {
+        file_len - bom_end
+    } else {
+        from.saturating_sub(bom_end)
+    };
+    let mut prog = SearchProgress::new(total);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let mut found = scan(con, &pat, bom_end, from, true, &mut prog)?;
+    if found.is_none() && con.def.wrapscan && !INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        found = scan(con, &pat, from, file_len, true, &mut prog)?;
+        if found.is_some() {
+            coutln!("Search hit TOP, continuing at BOTTOM.");
+        }
+    }
+    goto_search_hit(con, found)
+}
+
+fn goto_search_hit(con: &mut Ctx, found: Option<(u64, usize)>) -> CustRes<()> {
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    match found {
+        None => {
+            info!("{}", "Not found.");
+        }
+        Some((off, _)) => {
+            con.def.bookmark = off;
+            con.def.bookmark_end = con.def.bookmark;
+            show_page(con)?;
+        }
+    }
+    Ok(())
+}
+
+//note returns offset and length of first (or last if rev) match starting inside [lo, hi), None if not found or interrupted
+fn scan(
+    con: &mut Ctx,
+    pat: &SearchPat,
+    lo: u64,
+    hi: u64,
+    rev: bool,
+    prog: &mut SearchProgress,
+) -> CustRes<Option<(u64, usize)>> {
+    let mut found = None;
+    scan_par(
+        con,
+        pat,
+        lo,
+        hi,
+        rev,
+        prog,
+        |tr, chunk| {
+            let hit = find_in(tr, pat, &chunk.buf, chunk.cand.clone(), rev, chunk.sync)?;
+            Some((chunk.base + hit.0 as u64, hit.1))
+        },
+        |hit| {
+            found = hit;
+            found.is_some()
+        },
+    )?;
+    Ok(found)
+}
+
+//note returns up to max_cnt hits in the order of searching
+#[allow(clippy::too_many_arguments)]
+fn scan_hits(
+    con: &mut Ctx,
+    pat: &SearchPat,
+    lo: u64,
+    hi: u64,
+    rev: bool,
+    max_cnt: usize,
+    prog: &mut SearchProgress,
+) -> CustRes<Vec<(u64, usize)>> {
+    let mut retval = vec![];
+    scan_par(
+        con,
+        pat,
+        lo,
+        hi,
+        rev,
+        prog,
+        |tr, chunk| {
+            find_iter_in(tr, pat, &chunk.buf, chunk.cand.clone(), rev, chunk.sync)
+                .take(max_cnt)
+                .map(|(idx, len)| (chunk.base + idx as u64, len))
+                .collect::<Vec<_>>()
+        },
+        |hits| {
+            retval.extend(hits);
+            retval.len() >= max_cnt
+        },
+    )?;
+    retval.truncate(max_cnt);
+    Ok(retval)
+}
+
+//note the range is split into chunks which are scanned by work on worker threads round by round, results are passed to collect in the order of searching until it returns true
+//note so the earliest (or last if rev) hit is known after the round it is found in
+#[allow(clippy::too_many_arguments)]
+fn scan_par<T: Send>(
+    con: &mut Ctx,
+    pat: &SearchPat,
+    lo: u64,
+    hi: u64,
+    rev: bool,
+    prog: &mut SearchProgress,
+    work: impl Fn(&dyn TextRdr, &Chunk) -> T + Sync,
+    mut collect: impl FnMut(T) -> bool,
+) -> CustRes<()> {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let step = cmp::max(pat.max_len() * 0x100, SEARCH_CHUNK_LEN) as u64;
+    let nthreads = thread::available_parallelism().map_or(1, |cnt| cnt.get());
+    let tr = &*con.tr;
+    let fpath = &con.def.text_file_path;
+    let bom_end = con.def.bom_end;
+    let work = &work;
+    let mut cand_lo = lo;
+    let mut cand_hi = hi;
+    while cand_lo < cand_hi {
+        if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+            return Ok(());
+        }
+        //note chunks of candidate starts, in the order of searching
+        let mut bounds = vec![];
+        while bounds.len() < nthreads && cand_lo < cand_hi {
+            if rev {
+                let cs = cmp::max(cand_lo, cand_hi.saturating_sub(step));
+                bounds.push((cs, cand_hi));
+                cand_hi = cs;
+            } else {
+                let ce = cmp::min(cand_hi, cand_lo + step);
+                bounds.push((cand_lo, ce));
+                cand_lo = ce;
+            }
+        }
+        let results: Vec<io::Result<T>> = thread::scope(|scope| {
+            let workers: Vec<_> = bounds
+                .iter()
+                .map(|(cs, ce)| {
+                    let (cs, ce) = (*cs, *ce);
+                    scope.spawn(move || {
+                        let chunk = read_chunk(fpath, file_len, bom_end, pat.max_len(), cs, ce)?;
+                        Ok(work(tr, &chunk))
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        for res in results {
+            if collect(res?) {
+                return Ok(());
+            }
+        }
+        prog.advance(bounds.iter().map(|(cs, ce)| ce - cs).sum());
+    }
+    Ok(())
+}
+
+//note bytes read by a worker
+struct Chunk {
+    buf: Vec<u8>,
+    base: u64,               //note offset of buf[0] in file
+    cand: ops::Range<usize>, //note candidate starts of matches
+    sync: Option<usize>,     //note known char boundary
+}
+
+//note each chunk is read with more bytes on both sides, so that matches straddling chunk boundary are found, word boundaries can be checked and char boundaries can be resynchronized
+//note each worker opens the file on its own so reads do not contend for one file position
+fn read_chunk(
+    fpath: &path::Path,
+    file_len: u64,
+    bom_end: u64,
+    max_len: usize,
+    cs: u64,
+    ce: u64,
+) -> io::Result<Chunk> {
+    use std::io::*;
+    let rs = cs.saturating_sub(SEARCH_RESYNC_LEN as u64);
+    let re = cmp::min(file_len, ce + (max_len + SEARCH_CTX_LEN) as u64);
+    let mut buf = vec![0; (re - rs) as usize];
+    let mut fil = fs::File::open(fpath)?;
+    fil.seek(io::SeekFrom::Start(rs))?;
+    fil.read_exact(&mut buf)?;
+    Ok(Chunk {
+        buf,
+        base: rs,
+        cand: (cs - rs) as usize..(ce - rs) as usize,
+        sync: (rs <= bom_end).then(|| (bom_end - rs) as usize),
+    })
+}
+
+//note newlines are tracked inside the range of candidate starts of each chunk, so that lines spanning chunks are counted once
+#[derive(Default)]
+struct ChunkCount {
+    hits: u64,
+    lines: u64, //note distinct lines with hits, counting the line of first hit as new
+    has_nl: bool,
+    nl_before_first: bool,
+    nl_after_last: bool,
+    span: (u64, u64), //note range of candidate starts in file
+    first_start: u64, //note offset of first hit in file
+    last_end: u64,    //note offset right after last hit in file
+}
+
+//note hits starting before skip_to (offset in file) are left out, newlines are still tracked from start of candidates
+fn count_chunk(tr: &dyn TextRdr, pat: &SearchPat, chunk: &Chunk, skip_to: u64) -> ChunkCount {
+    let has_nl = |from: usize, to: usize| memchr::memchr(b'\n', &chunk.buf[from..to]).is_some();
+    let to_off = |idx: usize| chunk.base + idx as u64;
+    let mut retval = ChunkCount {
+        has_nl: has_nl(chunk.cand.start, chunk.cand.end),
+        span: (to_off(chunk.cand.start), to_off(chunk.cand.end)),
+        ..Default::default()
+    };
+    let skip = cmp::max(chunk.cand.start as u64, skip_to.saturating_sub(chunk.base));
+    let cand = cmp::min(skip, chunk.cand.end as u64) as usize..chunk.cand.end;
+    let mut prev: Option<usize> = None;
+    for (idx, len) in find_iter_in(tr, pat, &chunk.buf, cand, false, chunk.sync) {
+        match prev {
+            None => {
+                retval.nl_before_first = has_nl(chunk.cand.start, idx);
+                retval.lines = 1;
+            }
+            Some(prev_idx) => {
+                if has_nl(prev_idx, idx) {
+                    retval.lines += 1;
+                }
+            }
+        }
+        retval.hits += 1;
+        if prev.is_none() {
+            retval.first_start = to_off(idx);
+        }
+        retval.last_end = to_off(idx + len);
+        prev = Some(idx);
+    }
+    if let Some(prev_idx) = prev {
+        retval.nl_after_last = has_nl(prev_idx, chunk.cand.end);
+    }
+    retval
+}
+
+fn cmd_count(con: &mut Ctx) -> CustRes<()> {
+    let pat = match search_pat_of_iline(con, "count /".len()) {
+        None => {
+            return Ok(());
+        }
+        Some(inner) => inner,
+    };
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let bom_end = con.def.bom_end;
+    let started = time::Instant::now();
+    let mut prog = SearchProgress::new(file_len - bom_end);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let (hits, lines) = count_matches(con, &pat, &mut prog)?;
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Count interrupted.");
+        return Ok(());
+    }
+    coutln!(format!(
+        "{} matches on {} lines, {:.3}s",
+        hits,
+        lines,
+        started.elapsed().as_secs_f64()
+    ));
+    Ok(())
+}
+
+//note returns count of matches and lines containing them
+fn count_matches(con: &mut Ctx, pat: &SearchPat, prog: &mut SearchProgress) -> CustRes<(u64, u64)> {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let bom_end = con.def.bom_end;
+    let tr = con.tr.clone();
+    let fpath = con.def.text_file_path.clone();
+    let mut hits = 0;
+    let mut lines = 0;
+    let mut pending_nl = true; //note whether a newline is seen since last hit
+    let mut last_end = bom_end; //note end of last counted hit
+    let mut reread: io::Result<()> = Ok(());
+    scan_par(
+        con,
+        pat,
+        bom_end,
+        file_len,
+        false,
+        prog,
+        |tr, chunk| count_chunk(tr, pat, chunk, 0),
+        |mut cnt| {
+            //note a hit overlapping last hit of previous chunk is not counted (like two hits in one chunk), so the chunk is counted again after that hit
+            if cnt.hits > 0 && cnt.first_start < last_end {
+                cnt = match read_chunk(
+                    &fpath,
+                    file_len,
+                    bom_end,
+                    pat.max_len(),
+                    cnt.span.0,
+                    cnt.span.1,
+                ) {
+                    Err(err) => {
+                        reread = Err(err);
+                        return true;
+                    }
+                    Ok(chunk) => count_chunk(&*tr, pat, &chunk, last_end),
+                };
+            }
+            if cnt.hits == 0 {
+                pending_nl |= cnt.has_nl;
+                return false;
+            }
+            hits += cnt.hits;
+            lines += cnt.lines;
+            if !pending_nl && !cnt.nl_before_first {
+                lines -= 1;
+            }
+            pending_nl = cnt.nl_after_last;
+            last_end = cnt.last_end;
+            false
+        },
+    )?;
+    reread?;
+    Ok((hits, lines))
+}
+
+//note returns offset and length of first (or last if rev) match starting inside cand
+//note sync is a known char boundary in cont (if any)
+fn find_in(
+    tr: &dyn TextRdr,
+    pat: &SearchPat,
+    cont: &[u8],
+    cand: ops::Range<usize>,
+    rev: bool,
+    sync: Option<usize>,
+) -> Option<(usize, usize)> {
+    find_iter_in(tr, pat, cont, cand, rev, sync).next()
+}
+
+//note iterates over non-overlapping matches (like str::match_indices) starting inside cand, backward if rev
+fn find_iter_in<'a>(
+    tr: &'a dyn TextRdr,
+    pat: &'a SearchPat,
+    cont: &'a [u8],
+    cand: ops::Range<usize>,
+    rev: bool,
+    sync: Option<usize>,
+) -> Box<dyn Iterator<Item = (usize, usize)> + 'a> {
+    if cand.is_empty() {
+        return Box::new(iter::empty());
+    }
+    let hi = cand.end;
+    let reg_end = cmp::min(cont.len(), hi + pat.blob.len() - 1);
+    let finder = memchr::memmem::Finder::new(&pat.blob);
+    let (lower, upper) = match (pat.blob.first(), pat.fold_mask.first()) {
+        (Some(first), Some(true)) => (first.to_ascii_lowercase(), first.to_ascii_uppercase()),
+        (Some(first), _) => (*first, *first),
+        (None, _) => (0, 0),
+    };
+    //note for Unicode case folding, a char of the pattern that has no case is looked for, and a match starts 1 to 4 bytes per char before it
+    let anchor = pat
+        .folded
+        .iter()
+        .enumerate()
+        .filter(|(_, chr)| uncased(**chr))
+        .max_by_key(|(idx, chr)| (chr.len_utf8(), cmp::Reverse(*idx)))
+        .map(|(idx, chr)| {
+            let blob = chr.encode_utf8(&mut [0; 4]).as_bytes().to_vec();
+            (memchr::memmem::Finder::new(&blob).into_owned(), idx)
+        });
+    //note first candidate at or after pos
+    let next_cand = move |pos: usize| -> Option<usize> {
+        if !pat.folded.is_empty() {
+            match &anchor {
+                None => {
+                    //note no char without case, every char start is tried
+                    (pos..hi).find(|idx| cont[*idx] & 0b11000000 != 0b10000000)
+                }
+                Some((anchor, before)) => {
+                    let occ = pos + before + anchor.find(cont.get(pos + before..)?)?;
+                    let idx = cmp::max(pos, occ.saturating_sub(before * 4));
+                    (idx < hi).then_some(idx)
+                }
+            }
+        } else if pat.fold_mask.is_empty() {
+            //note prefilter candidates with SIMD-accelerated memchr/memmem, then verify each one
+            Some(pos + finder.find(cont.get(pos..reg_end)?)?)
+        } else {
+            Some(pos + memchr::memchr2(lower, upper, cont.get(pos..hi)?)?)
+        }
+    };
+    //note a rejected candidate might overlap a real match, so search goes on right after it rather than after the whole candidate
+    let mut pos = cand.start;
+    let mut bound = None;
+    let hits = iter::from_fn(move || {
+        while let Some(idx) = next_cand(pos) {
+            pos = idx + 1;
+            let len = match pat.match_at(cont, idx) {
+                None => {
+                    continue;
+                }
+                Some(inner) => inner,
+            };
+            let (at_bound, before) = chr_boundary_at(tr, cont, idx, sync, &mut bound);
+            if !at_bound {
+                continue;
+            }
+            if pat.word && !at_word_bounds(tr, pat.utf8, cont, idx, idx + len, before) {
+                continue;
+            }
+            //note matches found forward do not overlap each other
+            if !rev {
+                pos = idx + cmp::max(len, 1);
+            }
+            return Some((idx, len));
+        }
+        None
+    });
+    if rev {
+        //note every match start is collected going forward then handed out backward
+        let mut hits: Vec<_> = hits.collect();
+        hits.reverse();
+        return Box::new(hits.into_iter());
+    }
+    Box::new(hits)
+}
+
+//note a match starting in the middle of a multi-byte char is a false positive (e.g. GB18030 trailing byte looks like ASCII)
+//note resynchronizes from nearest known char boundary and walks forward char by char
+//note bound is the last boundary found by previous call, candidates going forward start from it so every byte is walked once
+//note returns whether idx is at a char boundary, and start of the char right before idx (None at start of text), which tells word boundary in encodings where a trailing byte looks like ASCII
+fn chr_boundary_at(
+    tr: &dyn TextRdr,
+    cont: &[u8],
+    idx: usize,
+    sync: Option<usize>,
+    bound: &mut Option<usize>,
+) -> (bool, Option<usize>) {
+    let from = match *bound {
+        Some(inner) if inner <= idx => inner,
+        _ => match (tr.sync_before(cont, idx), sync.filter(|pos| *pos <= idx)) {
+            (Some(lhs), Some(rhs)) => cmp::max(lhs, rhs),
+            (Some(inner), None) | (None, Some(inner)) => inner,
+            //note no way to tell, so walk from start of cont, SEARCH_RESYNC_LEN bytes before chunk make a misaligned walk rare
+            (None, None) => 0,
+        },
+    };
+    let mut pos = from;
+    let mut last = from;
+    while pos < idx {
+        last = pos;
+        pos += tr.chr_len(cont, pos);
+    }
+    *bound = Some(if pos == idx { idx } else { last });
+    let before = if from < idx {
+        Some(last)
+    } else {
+        //note a boundary told right at idx is after a byte that is never a trailing byte
+        idx.checked_sub(1).filter(|_| sync != Some(idx))
+    };
+    (pos == idx, before)
+}
+
+//note boundary is only required at the side where the match begins (or ends) with a word char, like \< and \> in vim
+//note before is start of the char right before start, UTF-8 does not need it as a char can be told from its own bytes
+fn at_word_bounds(
+    tr: &dyn TextRdr,
+    utf8: bool,
+    cont: &[u8],
+    start: usize,
+    end: usize,
+    before: Option<usize>,
+) -> bool {
+    let (word_before_start, word_before_end) = if utf8 {
+        (word_chr_before(cont, start), word_chr_before(cont, end))
+    } else {
+        //note last char of the match is found by walking from its start
+        let mut last = start;
+        let mut pos = start;
+        while pos < end {
+            last = pos;
+            pos += tr.chr_len(cont, pos);
+        }
+        (
+            before.is_some_and(|pos| word_chr_at(utf8, cont, pos)),
+            word_chr_at(utf8, cont, last),
+        )
+    };
+    if word_chr_at(utf8, cont, start) && word_before_start {
+        return false;
+    }
+    if word_before_end && word_chr_at(utf8, cont, end) {
+        return false;
+    }
+    true
+}
+
+//note non-ASCII chars count as word chars in encodings other than UTF-8 (e.g. Chinese chars in GB18030)
+fn word_chr_at(utf8: bool, cont: &[u8], idx: usize) -> bool {
+    match cont.get(idx) {
+        None => false,
+        Some(byt) if byt.is_ascii() => byt.is_ascii_alphanumeric() || *byt == b'_',
+        Some(_) if !utf8 => true,
+        Some(_) => decode_utf8_at(cont, idx).is_some_and(|(chr, _)| chr.is_alphanumeric()),
+    }
+}
+
+//note UTF-8 only
+fn word_chr_before(cont: &[u8], idx: usize) -> bool {
+    if idx == 0 {
+        return false;
+    }
+    if cont[idx - 1].is_ascii() {
+        return word_chr_at(true, cont, idx - 1);
+    }
+    //note skip back over continuation bytes to the leading byte
+    let mut lead = idx - 1;
+    while lead > 0 && idx - lead < 4 && cont[lead] & 0b11000000 == 0b10000000 {
+        lead -= 1;
+    }
+    match decode_utf8_at(cont, lead) {
+        Some((chr, clen)) if lead + clen == idx => chr.is_alphanumeric(),
+        _ => false,
+    }
+}
+
+//note Unicode simple case folding (one char to one char), so e.g. final sigma matches sigma, and sharp s matches capital sharp s but not ss
+//note lowercase mapping gives the same result except for chars listed here, whose lowercase form is not what their uppercase form maps to
+fn fold_chr(chr: char) -> char {
+    match chr {
+        'ς' => 'σ',
+        'ϐ' => 'β',
+        'ϑ' => 'θ',
+        'ϕ' => 'φ',
+        'ϖ' => 'π',
+        'ϰ' => 'κ',
+        'ϱ' => 'ρ',
+        'ϵ' => 'ε',
+        'ſ' => 's',
+        'ẛ' => 'ṡ',
+        '\u{345}' | '\u{1FBE}' => 'ι',
+        '\u{1C80}' => 'в',
+        '\u{1C81}' => 'д',
+        '\u{1C82}' => 'о',
+        '\u{1C83}' => 'с',
+        '\u{1C84}' | '\u{1C85}' => 'т',
+        '\u{1C86}' => 'ъ',
+        '\u{1C87}' => 'ѣ',
+        '\u{1C88}' => '\u{A64B}',
+        _ => {
+            //note a char lowercased to more than one char (e.g. U+0130) has no simple folding
+            let mut lower = chr.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(inner), None) => inner,
+                _ => chr,
+            }
+        }
+    }
+}
+
+//note a char other than its own case variants never folds to it, so its bytes appear in every match as is
+fn uncased(chr: char) -> bool {
+    chr.to_lowercase().eq([chr]) && chr.to_uppercase().eq([chr])
+}
+
+fn decode_utf8_at(cont: &[u8], idx: usize) -> Option<(char, usize)> {
+    let clen = match *cont.get(idx)? {
+        0..=0x7F => 1,
+        0b11000000..=0b11011111 => 2,
+        0b11100000..=0b11101111 => 3,
+        0b11110000..=0b11110111 => 4,
+        _ => {
+            return None;
+        }
+    };
+    let cstr = str::from_utf8(cont.get(idx..idx + clen)?).ok()?;
+    Some((cstr.chars().next()?, clen))
+}
+
+fn cmd_filter(con: &mut Ctx) -> CustRes<()> {
+    if con.def.iline == "&" {
+        con.def.filter = None;
+    } else {
+        con.def.filter = match search_pat_of_iline(con, 1) {
+            None => {
+                return Ok(());
+            }
+            Some(inner) => Some(inner),
+        };
+    }
+    con.def.bookmark_end = con.def.bookmark;
+    show_page(con)
+}
+
+//note a line containing a match, or only a window around the match if the line is too long
+struct FilterLine {
+    start: u64,
+    end: u64,  //note excluding newline
+    next: u64, //note where searching for next line starts
+    cut_head: bool,
+}
+
+//note the line is searched for newlines no further than half a page of bytes away from the match, and never before floor
+fn filter_line_at(con: &mut Ctx, hit: u64, hit_len: usize, floor: u64) -> CustRes<FilterLine> {
+    use std::io::*;
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let side = (text_width(con) * con.def_dheight / 2) as u64;
+    let bom_end = con.def.bom_end;
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let lo = cmp::max(floor, hit.saturating_sub(side));
+    //note one more byte before lo for telling whether lo is start of line
+    let rd_lo = if lo > bom_end { lo - 1 } else { lo };
+    let mut buf = vec![0; (hit - rd_lo) as usize];
+    fil.seek(io::SeekFrom::Start(rd_lo))?;
+    fil.read_exact(&mut buf)?;
+    let (start, cut_head) = match memchr::memrchr(b'\n', &buf) {
+        None => (lo, lo > bom_end),
+        Some(pos) => (rd_lo + pos as u64 + 1, false),
+    };
+    let tail = hit + hit_len as u64;
+    let hi = cmp::min(file_len, tail + side);
+    let mut buf = vec![0; (hi - tail) as usize];
+    fil.seek(io::SeekFrom::Start(tail))?;
+    fil.read_exact(&mut buf)?;
+    let (end, next) = match memchr::memchr(b'\n', &buf) {
+        None => (hi, hi),
+        Some(pos) => (tail + pos as u64, tail + pos as u64 + 1),
+    };
+    Ok(FilterLine {
+        start,
+        end,
+        next,
+        cut_head,
+    })
+}
+
+//note display lines of a filtered line, a window cut in the middle of a multi-byte char is shrunk to char boundaries
+fn filter_dlines(con: &mut Ctx, fl: &FilterLine) -> CustRes<Vec<String>> {
+    use std::io::*;
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut buf = vec![0; (fl.end - fl.start) as usize];
+    fil.seek(io::SeekFrom::Start(fl.start))?;
+    fil.read_exact(&mut buf)?;
+    let tr = con.tr.clone();
+    let skip = if fl.cut_head {
+        (1..buf.len())
+            .find(|pos| tr.sync_before(&buf, *pos) == Some(*pos))
+            .unwrap_or(buf.len())
+    } else {
+        0
+    };
+    let mut take = skip;
+    while take < buf.len() && take + tr.chr_len(&buf, take) <= buf.len() {
+        take += tr.chr_len(&buf, take);
+    }
+    let cont = &buf[skip..take];
+    let base = fl.start + skip as u64;
+    find_hits(con, cont, base);
+    find_colors(con, cont, base);
+    let nl_before = if con.def.nu && !fl.cut_head {
+        nl_count_before(con, base)?
+    } else {
+        None
+    };
+    let dlwidth = text_width(con);
+    let mut retval = vec![];
+    let mut lin = if fl.cut_head { " " } else { "$" }.to_owned();
+    let mut lin_width = 1;
+    let mut lin_off = base;
+    for (off, cstr) in tr.buf2strs(cont, cont.len(), con, true) {
+        if cstr.is_empty() {
+            continue;
+        }
+        let cell_width = if cstr == "\t" {
+            tab_width(con, lin_width)
+        } else {
+            disp_width(con, &cstr)
+        };
+        if lin_width > 1 && lin_width + cell_width > dlwidth {
+            let lstart = retval.is_empty() && !fl.cut_head;
+            retval.push(gutter(con, lstart, nl_before, lin_off) + &lin);
+            lin = " ".to_owned();
+            lin_width = 1;
+            lin_off = base + off as u64;
+        }
+        if cstr == "\t" {
+            push_hl(con, &mut lin, base + off as u64, &" ".repeat(cell_width));
+        } else {
+            push_hl(con, &mut lin, base + off as u64, &cstr);
+        }
+        lin_width += cell_width;
+    }
+    let lstart = retval.is_empty() && !fl.cut_head;
+    retval.push(gutter(con, lstart, nl_before, lin_off) + &lin);
+    Ok(retval)
+}
+
+//note filter view only renders lines containing matches, a line not fitting in the rest of page is left for next page
+fn show_filter_page(con: &mut Ctx) -> CustRes<()> {
+    let pat = con.def.filter.clone().unwrap();
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    if con.def.bookmark < con.def.bom_end {
+        con.def.bookmark = con.def.bom_end;
+    }
+    let mut prog = SearchProgress::new(file_len - con.def.bookmark);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let mut pos = con.def.bookmark;
+    let mut dlines: Vec<String> = vec![];
+    let mut eof = false;
+    'page: while dlines.len() < con.def_dheight {
+        let hits = scan_hits(con, &pat, pos, file_len, false, con.def_dheight, &mut prog)?;
+        if hits.is_empty() {
+            eof = !INTERRUPTED.load(sync::atomic::Ordering::Relaxed);
+            break;
+        }
+        for (hit, len) in hits {
+            if hit < pos {
+                //note another match in a line already shown
+                continue;
+            }
+            let fl = filter_line_at(con, hit, len, pos)?;
+            let lines = filter_dlines(con, &fl)?;
+            let room = con.def_dheight - dlines.len();
+            if lines.len() > room && !dlines.is_empty() {
+                break 'page;
+            }
+            dlines.extend(lines.into_iter().take(room));
+            pos = fl.next;
+            if dlines.len() == con.def_dheight {
+                break 'page;
+            }
+        }
+    }
+    for lin in &dlines {
+        cout_dline(con, lin);
+    }
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+    } else if eof {
+        cout_dline(con, "END-OF-FILE");
+        pos = file_len;
+    }
+    con.def.bookmark_end = pos;
+    cout_status_if_needed(con);
+    Ok(())
+}
+
+//note collects matching lines backward until the page is full, then renders forward from the earliest one
+fn show_filter_prev_page(con: &mut Ctx) -> CustRes<()> {
+    let pat = con.def.filter.clone().unwrap();
+    let bom_end = con.def.bom_end;
+    let mut pos = con.def.bookmark_end;
+    let mut prog = SearchProgress::new(pos.saturating_sub(bom_end));
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let mut used = 0;
+    let mut earliest = None;
+    'page: while used < con.def_dheight {
+        let hits = scan_hits(con, &pat, bom_end, pos, true, con.def_dheight, &mut prog)?;
+        if hits.is_empty() {
+            break;
+        }
+        for (hit, len) in hits {
+            if hit >= pos {
+                continue;
+            }
+            let fl = filter_line_at(con, hit, len, bom_end)?;
+            let cnt = filter_dlines(con, &fl)?.len();
+            if used + cnt > con.def_dheight && used > 0 {
+                break 'page;
+            }
+            used += cnt;
+            earliest = Some(fl.start);
+            pos = fl.start;
+        }
+    }
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    match earliest {
+        None => {
+            info!("{}", "Top of file reached.");
+            Ok(())
+        }
+        Some(inner) => {
+            con.def.bookmark = inner;
+            show_filter_page(con)
+        }
+    }
+}
+
+//note Ctrl-C only cancels search while searching, otherwise it terminates the program as usual
+#[cfg(unix)]
+fn catch_sigint(on: bool) {
+    INTERRUPTED.store(false, sync::atomic::Ordering::Relaxed);
+    let handler = if on {
+        on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t
+    } else {
+        libc::SIG_DFL
+    };
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+#[cfg(not(unix))]
+fn catch_sigint(_on: bool) {}
+
+//note compiled form of search pattern
+#[derive(Clone, Debug, Default)]
+struct SearchPat {
+    blob: Vec<u8>,        //note encoded pattern
+    fold_mask: Vec<bool>, //note which bytes of blob are compared case-insensitively, empty if case-sensitive
+    folded: Vec<char>, //note pattern after Unicode simple case folding (UTF-8 only), empty if ASCII folding is enough
+    word: bool,
+    utf8: bool, //note whether text is UTF-8, for telling word chars
+}
+impl SearchPat {
+    //note max bytes a match can take in file, case folding might change the length (e.g. KELVIN SIGN vs k)
+    fn max_len(&self) -> usize {
+        if self.folded.is_empty() {
+            self.blob.len()
+        } else {
+            self.folded.len() * 4
+        }
+    }
+    //note returns length of match starting at idx, word boundaries are not checked here
+    fn match_at(&self, cont: &[u8], idx: usize) -> Option<usize> {
+        if !self.folded.is_empty() {
+            return self.fold_match_at(cont, idx);
+        }
+        let win = cont.get(idx..idx + self.blob.len())?;
+        let matched = if self.fold_mask.is_empty() {
+            win == self.blob
+        } else {
+            win.iter()
+                .zip(&self.blob)
+                .zip(&self.fold_mask)
+                .all(|((lhs, rhs), fold)| lhs == rhs || (*fold && lhs.eq_ignore_ascii_case(rhs)))
+        };
+        matched.then_some(self.blob.len())
+    }
+    fn fold_match_at(&self, cont: &[u8], idx: usize) -> Option<usize> {
+        let mut pos = idx;
+        let mut cnt = 0;
+        while cnt < self.folded.len() {
+            let (chr, clen) = decode_utf8_at(cont, pos)?;
+            if fold_chr(chr) != self.folded[cnt] {
+                return None;
+            }
+            cnt += 1;
+            pos += clen;
+        }
+        Some(pos - idx)
+    }
+}
+
+struct SearchProgress {
+    total: u64, //note bytes to be scanned
+    scanned: u64,
+    started: time::Instant,
+    reported: time::Instant,
+}
+impl SearchProgress {
+    fn new(total: u64) -> Self {
+        let now = time::Instant::now();
+        Self {
+            total,
+            scanned: 0,
+            started: now,
+            reported: now,
+        }
+    }
+    //note only prints when scanning takes long enough
+    fn advance(&mut self, len: u64) {
+        self.scanned += len;
+        if self.reported.elapsed() < SEARCH_PROGRESS_INTERVAL {
+            return;
+        }
+        self.reported = time::Instant::now();
+        let perc = (self.scanned * 100).checked_div(self.total).unwrap_or(100);
+        let mibps = self.scanned as f64 / self.started.elapsed().as_secs_f64() / 1048576.0;
+        eprintln!(
+            "Searching: {} bytes scanned ({}%), {:.1} MiB/s, Ctrl-C to cancel",
+            self.scanned, perc, mibps
+        );
+    }
+}
+
+//note streaming JSON tokenizer, only brackets, commas and colons outside strings are structural
+#[derive(Copy, Clone, Debug, Default)]
+struct JsonTok {
+    in_str: bool,
+    esc: bool,
+}
+impl JsonTok {
+    //note returns the byte if it is structural
+    fn step(&mut self, byt: u8) -> Option<u8> {
+        if self.in_str {
+            if self.esc {
+                self.esc = false;
+            } else if byt == b'\\' {
+                self.esc = true;
+            } else if byt == b'"' {
+                self.in_str = false;
+            }
+            return None;
+        }
+        match byt {
+            b'"' => {
+                self.in_str = true;
+                None
+            }
+            b'{' | b'[' | b'}' | b']' | b',' | b':' => Some(byt),
+            _ => None,
+        }
+    }
+}
+
+fn is_json_ws(byt: u8) -> bool {
+    matches!(byt, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+//note returns offset of start of the real line containing off, lines are never loaded as a whole
+fn line_start_before(con: &mut Ctx, off: u64) -> CustRes<u64> {
+    let bom_end = con.def.bom_end;
+    line_start_after(con, bom_end, off)
+}
+
+//note like line_start_before but scans back no further than floor, which is returned if no newline lies between it and off
+fn line_start_after(con: &mut Ctx, floor: u64, off: u64) -> CustRes<u64> {
+    use std::io::*;
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut hi = off;
+    while hi > floor {
+        let lo = cmp::max(floor, hi.saturating_sub(SEARCH_CHUNK_LEN as u64));
+        let mut buf = vec![0; (hi - lo) as usize];
+        fil.seek(io::SeekFrom::Start(lo))?;
+        fil.read_exact(&mut buf)?;
+        if let Some(pos) = memchr::memrchr(b'\n', &buf) {
+            return Ok(lo + pos as u64 + 1);
+        }
+        hi = lo;
+    }
+    Ok(floor)
+}
+
+//note tokenizer state of a JSON or XML scan at some offset, with start of the real line the scan started at
+#[derive(Clone, Debug)]
+enum Checkpoint {
+    Json(u64, Vec<u64>, JsonTok),
+    Xml(u64, usize, XmlTok),
+}
+
+//note returns start of the real line containing off and nearest checkpoint in that line at or before off, so that jm/jn/ju/pp need not rescan from the line start every time
+fn line_start_cached(con: &mut Ctx, off: u64) -> CustRes<(u64, Option<(u64, Checkpoint)>)> {
+    let near = con
+        .def
+        .checkpoints
+        .range(..=off)
+        .next_back()
+        .map(|(at, cp)| (*at, cp.clone()));
+    let floor = cmp::max(con.def.bom_end, near.as_ref().map_or(0, |(at, _)| *at));
+    let lstart = line_start_after(con, floor, off)?;
+    match near {
+        Some((at, cp)) if at == lstart => {
+            let cp_lstart = match cp {
+                Checkpoint::Json(cp_lstart, ..) | Checkpoint::Xml(cp_lstart, ..) => cp_lstart,
+            };
+            Ok((cp_lstart, Some((at, cp))))
+        }
+        _ => Ok((lstart, None)),
+    }
+}
+
+//note feeds chars (with their offsets) from `from` to f until f returns false (then returns true) or end of file, a multi-byte char is fed as a whole so that e.g. GB18030 trailing bytes are never taken as brackets
+fn walk_chars(
+    con: &mut Ctx,
+    from: u64,
+    prog: &mut SearchProgress,
+    mut f: impl FnMut(u64, &[u8]) -> bool,
+) -> CustRes<bool> {
+    use std::io::*;
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let tr = con.tr.clone();
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut pos = from;
+    while pos < file_len {
+        if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let hi = cmp::min(file_len, pos + SEARCH_CHUNK_LEN as u64);
+        let mut buf = vec![0; (hi - pos) as usize];
+        fil.seek(io::SeekFrom::Start(pos))?;
+        fil.read_exact(&mut buf)?;
+        let mut idx = 0;
+        while idx < buf.len() {
+            let len = if buf[idx] < 0x80 {
+                1
+            } else {
+                tr.chr_len(&buf, idx)
+            };
+            if idx + len > buf.len() && hi < file_len {
+                //note the char is continued in next chunk
+                break;
+            }
+            if !f(pos + idx as u64, &buf[idx..cmp::min(idx + len, buf.len())]) {
+                return Ok(true);
+            }
+            idx += len;
+        }
+        if idx == 0 {
+            //note never happens for chunks longer than any char, just for sure
+            idx = buf.len();
+        }
+        prog.advance(idx as u64);
+        pos += idx as u64;
+    }
+    Ok(false)
+}
+
+//note returns offsets of opening brackets of containers enclosing off and tokenizer state at off, scanning from start of real line (a JSON document is assumed to start there)
+fn json_state_at(
+    con: &mut Ctx,
+    off: u64,
+    prog: &mut SearchProgress,
+) -> CustRes<(Vec<u64>, JsonTok)> {
+    let (lstart, near) = line_start_cached(con, off)?;
+    let (from, mut stack, mut tok) = match near {
+        Some((at, Checkpoint::Json(_, stack, tok))) => (at, stack, tok),
+        _ => (lstart, vec![], JsonTok::default()),
+    };
+    walk_chars(con, from, prog, |pos, chr| {
+        let byt = chr[0];
+        if pos >= off {
+            return false;
+        }
+        match tok.step(byt) {
+            Some(b'{') | Some(b'[') => {
+                stack.push(pos);
+            }
+            Some(b'}') | Some(b']') => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        true
+    })?;
+    if !INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        let cp = Checkpoint::Json(lstart, stack.clone(), tok);
+        con.def.checkpoints.insert(off, cp);
+    }
+    Ok((stack, tok))
+}
+
+//note first structural byte (or start of a string or scalar) at or after off, None if whitespace lasts till end of file
+fn json_token_at(con: &mut Ctx, off: u64, prog: &mut SearchProgress) -> CustRes<Option<(u64, u8)>> {
+    let mut retval = None;
+    walk_chars(con, off, prog, |pos, chr| {
+        let byt = chr[0];
+        if is_json_ws(byt) {
+            return true;
+        }
+        retval = Some((pos, byt));
+        false
+    })?;
+    Ok(retval)
+}
+
+//note jm jumps between matching brackets, jn to next sibling (key in object or element in array), ju to opening bracket of parent container
+fn cmd_json(con: &mut Ctx) -> CustRes<()> {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let mut prog = SearchProgress::new(file_len);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let (mut stack, mut tok) = json_state_at(con, con.def.bookmark, &mut prog)?;
+    let cur = if tok.in_str {
+        None
+    } else {
+        json_token_at(con, con.def.bookmark, &mut prog)?
+    };
+    let mut target = None;
+    let mut depth = 0usize;
+    let not_found = match con.def.iline.as_str() {
+        "jm" => match cur {
+            Some((pos, b'{')) | Some((pos, b'[')) => {
+                walk_chars(con, pos, &mut prog, |pos, chr| {
+                    let byt = chr[0];
+                    match tok.step(byt) {
+                        Some(b'{') | Some(b'[') => {
+                            depth += 1;
+                        }
+                        Some(b'}') | Some(b']') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                target = Some(pos);
+                                return false;
+                            }
+                        }
+                        _ => {}
+                    }
+                    true
+                })?;
+                "Matching bracket not found."
+            }
+            Some((_, b'}')) | Some((_, b']')) => {
+                target = stack.last().copied();
+                "Matching bracket not found."
+            }
+            _ => "Not on a bracket.",
+        },
+        "jn" => {
+            let mut after_comma = false;
+            walk_chars(con, con.def.bookmark, &mut prog, |pos, chr| {
+                let byt = chr[0];
+                if after_comma {
+                    if is_json_ws(byt) {
+                        return true;
+                    }
+                    target = Some(pos);
+                    return false;
+                }
+                match tok.step(byt) {
+                    Some(b'{') | Some(b'[') => {
+                        depth += 1;
+                    }
+                    Some(b'}') | Some(b']') => {
+                        if depth == 0 {
+                            return false;
+                        }
+                        depth -= 1;
+                    }
+                    Some(b',') if depth == 0 => {
+                        after_comma = true;
+                    }
+                    _ => {}
+                }
+                true
+            })?;
+            "No next sibling."
+        }
+        _ => {
+            if let Some((_, b'}')) | Some((_, b']')) = cur {
+                //note a closing bracket belongs to its own container
+                stack.pop();
+            }
+            target = stack.last().copied();
+            "No parent container."
+        }
+    };
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    let inner = match target {
+        None => {
+            coutln!(not_found);
+            return Ok(());
+        }
+        Some(inner) => inner,
+    };
+    con.def.bookmark = inner;
+    con.def.bookmark_end = inner;
+    if con.def.json_pp {
+        let (stack, tok) = json_state_at(con, inner, &mut prog)?;
+        let limit = (con.def_dheight * con.def_dlwidth * 4) as u64;
+        let pretty = json_pretty(
+            con,
+            inner,
+            stack.len(),
+            tok,
+            limit,
+            con.def_dheight,
+            &mut prog,
+        )?;
+        cout_pretty(con, &pretty, false);
+    } else {
+        show_page(con)?;
+    }
+    Ok(())
+}
+
+//note re-indents JSON from off (starting at depth with tokenizer state tok) until limit bytes are taken or max_lines lines are produced, whitespace outside strings is dropped
+#[allow(clippy::too_many_arguments)]
+fn json_pretty(
+    con: &mut Ctx,
+    off: u64,
+    depth: usize,
+    tok: JsonTok,
+    limit: u64,
+    max_lines: usize,
+    prog: &mut SearchProgress,
+) -> CustRes<Vec<u8>> {
+    let mut depth = depth;
+    let mut tok = tok;
+    let mut retval = vec![b' '; depth * PP_INDENT];
+    let mut lines = 0;
+    let mut pending_nl = false;
+    macro_rules! newline {
+        () => {
+            retval.push(b'\n');
+            retval.extend(iter::repeat_n(b' ', depth * PP_INDENT));
+            lines += 1;
+        };
+    }
+    walk_chars(con, off, prog, |pos, chr| {
+        if pos - off >= limit || lines >= max_lines {
+            return false;
+        }
+        let byt = chr[0];
+        let was_in_str = tok.in_str;
+        let structural = tok.step(byt);
+        if !was_in_str && !tok.in_str && is_json_ws(byt) {
+            return true;
+        }
+        if pending_nl && !matches!(structural, Some(b'}') | Some(b']')) {
+            newline!();
+        }
+        pending_nl = false;
+        match structural {
+            Some(b'{') | Some(b'[') => {
+                retval.push(byt);
+                depth += 1;
+                pending_nl = true;
+            }
+            Some(b'}') | Some(b']') => {
+                let empty = retval
+                    .last()
+                    .is_some_and(|lst| *lst == b'{' || *lst == b'[');
+                depth = depth.saturating_sub(1);
+                if !empty {
+                    newline!();
+                }
+                retval.push(byt);
+            }
+            Some(b',') => {
+                retval.push(byt);
+                newline!();
+            }
+            Some(b':') => {
+                retval.extend(b": ");
+            }
+            _ => {
+                retval.extend(chr);
+            }
+        }
+        true
+    })?;
+    Ok(retval)
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+enum XmlTag {
+    #[default]
+    Open,
+    Close,
+    Empty, //note self-closing, e.g. <br/>
+    Other, //note e.g. comment, CDATA, processing instruction, DOCTYPE
+}
+
+//note streaming XML tokenizer, only tells where markup starts and ends
+#[derive(Copy, Clone, Debug, Default)]
+struct XmlTok {
+    in_tag: bool,
+    kind: XmlTag,
+    len: usize,          //note bytes of markup so far
+    quote: u8,           //note quote of attribute value being inside, 0 if none
+    term: &'static [u8], //note bytes ending the markup
+    tail: [u8; 2],       //note last 2 bytes
+}
+impl XmlTok {
+    //note returns kind of markup if it ends at the byte
+    fn step(&mut self, byt: u8) -> Option<XmlTag> {
+        if !self.in_tag {
+            if byt == b'<' {
+                *self = Self {
+                    in_tag: true,
+                    len: 1,
+                    term: b">",
+                    ..Default::default()
+                };
+            }
+            return None;
+        }
+        self.len += 1;
+        let tail = self.tail;
+        self.tail = [tail[1], byt];
+        match (self.len, byt) {
+            (2, b'/') => {
+                self.kind = XmlTag::Close;
+            }
+            (2, b'?') => {
+                self.kind = XmlTag::Other;
+                self.term = b"?>";
+            }
+            (2, b'!') => {
+                self.kind = XmlTag::Other;
+            }
+            (3, b'-') if self.kind == XmlTag::Other && tail[1] == b'!' => {
+                self.term = b"-->";
+            }
+            (3, b'[') if self.kind == XmlTag::Other && tail[1] == b'!' => {
+                self.term = b"]]>";
+            }
+            _ => {}
+        }
+        if self.term == b">" {
+            if self.quote != 0 {
+                if byt == self.quote {
+                    self.quote = 0;
+                }
+                return None;
+            }
+            if byt == b'"' || byt == b'\'' {
+                self.quote = byt;
+                return None;
+            }
+        }
+        if byt != b'>' || !tail.ends_with(&self.term[..self.term.len() - 1]) {
+            return None;
+        }
+        self.in_tag = false;
+        if self.kind == XmlTag::Open && tail[1] == b'/' {
+            return Some(XmlTag::Empty);
+        }
+        Some(self.kind)
+    }
+}
+
+//note returns nesting depth and tokenizer state at off, scanning from start of real line (an XML document is assumed to start there)
+fn xml_state_at(con: &mut Ctx, off: u64, prog: &mut SearchProgress) -> CustRes<(usize, XmlTok)> {
+    let (lstart, near) = line_start_cached(con, off)?;
+    let (from, mut depth, mut tok) = match near {
+        Some((at, Checkpoint::Xml(_, depth, tok))) => (at, depth, tok),
+        _ => (lstart, 0usize, XmlTok::default()),
+    };
+    walk_chars(con, from, prog, |pos, chr| {
+        if pos >= off {
+            return false;
+        }
+        match tok.step(chr[0]) {
+            Some(XmlTag::Open) => {
+                depth += 1;
+            }
+            Some(XmlTag::Close) => {
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        true
+    })?;
+    if !INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        con.def
+            .checkpoints
+            .insert(off, Checkpoint::Xml(lstart, depth, tok));
+    }
+    Ok((depth, tok))
+}
+
+//note re-indents XML from off until limit bytes are taken, each markup goes to its own line except that an element only containing text stays in one line, whitespace around text is dropped
+fn xml_pretty(
+    con: &mut Ctx,
+    off: u64,
+    depth: usize,
+    tok: XmlTok,
+    limit: u64,
+    prog: &mut SearchProgress,
+) -> CustRes<Vec<u8>> {
+    let mut depth = depth;
+    let mut tok = tok;
+    let mut retval = vec![];
+    let mut text = vec![];
+    let mut tag = vec![];
+    let mut last = XmlTag::Other; //note kind of last markup written, a closing tag right after an opening tag stays in the same line
+    macro_rules! newline {
+        () => {
+            if !retval.is_empty() {
+                retval.push(b'\n');
+            }
+            retval.extend(iter::repeat_n(b' ', depth * PP_INDENT));
+        };
+    }
+    walk_chars(con, off, prog, |pos, chr| {
+        if pos - off >= limit {
+            return false;
+        }
+        let was_in_tag = tok.in_tag;
+        let kind = tok.step(chr[0]);
+        if !was_in_tag && !tok.in_tag {
+            text.extend(chr);
+            return true;
+        }
+        if !was_in_tag {
+            let trimmed = text.trim_ascii();
+            if !trimmed.is_empty() {
+                if last != XmlTag::Open || retval.is_empty() {
+                    newline!();
+                    last = XmlTag::Other;
+                }
+                retval.extend(trimmed);
+            }
+            text.clear();
+        }
+        tag.extend(chr);
+        let kind = match kind {
+            None => {
+                return true;
+            }
+            Some(inner) => inner,
+        };
+        match kind {
+            XmlTag::Open => {
+                newline!();
+                depth += 1;
+            }
+            XmlTag::Close => {
+                depth = depth.saturating_sub(1);
+                if last != XmlTag::Open || retval.is_empty() {
+                    newline!();
+                }
+            }
+            _ => {
+                newline!();
+            }
+        }
+        retval.append(&mut tag);
+        last = kind;
+        true
+    })?;
+    let trimmed = text.trim_ascii();
+    if !trimmed.is_empty() {
+        newline!();
+        retval.extend(trimmed);
+    }
+    retval.append(&mut tag);
+    Ok(retval)
+}
+
+//note the file is never modified, re-indented text is only printed
+fn cmd_pp(con: &mut Ctx) -> CustRes<()> {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let mut prog = SearchProgress::new(file_len);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let off = cmp::max(con.def.bookmark, con.def.bom_end);
+    let xml = match con.def.iline.as_str() {
+        "pp json" => false,
+        "pp xml" => true,
+        _ => {
+            let (lstart, _) = line_start_cached(con, off)?;
+            json_token_at(con, lstart, &mut prog)?.is_some_and(|(_, byt)| byt == b'<')
+        }
+    };
+    let limit = con.def.pp_limit as u64;
+    let pretty = if xml {
+        let (depth, tok) = xml_state_at(con, off, &mut prog)?;
+        xml_pretty(con, off, depth, tok, limit, &mut prog)?
+    } else {
+        let (stack, tok) = json_state_at(con, off, &mut prog)?;
+        json_pretty(con, off, stack.len(), tok, limit, usize::MAX, &mut prog)?
+    };
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    cout_pretty(con, &pretty, off + limit < file_len);
+    Ok(())
+}
+
+//note renders bytes not from the file (e.g. re-indented text) through the usual display-line wrapping page by page, without gutter or highlighting
+//note it is printed even when pages are muted (full-screen mode redraws the page after a command, but this is not the page)
+fn cout_pretty(con: &mut Ctx, cont: &[u8], limited: bool) {
+    let muted = con.def.muted.replace(false);
+    //note search hits and colored spans are offsets in file, they mean nothing in re-indented text
+    let saved = (
+        con.def.nu,
+        con.def.offsets,
+        mem::take(&mut con.def.page_hits),
+        mem::take(&mut con.def.page_colors),
+        con.def.page_at_lstart,
+    );
+    con.def.nu = false;
+    con.def.offsets = None;
+    let tr = con.tr.clone();
+    let mut pos = 0;
+    while pos < cont.len() {
+        con.def.page_at_lstart = pos == 0;
+        let rlen = tr.render(&cont[pos..], cont.len() - pos, con);
+        if rlen == 0 {
+            break;
+        }
+        pos += rlen;
+    }
+    (
+        con.def.nu,
+        con.def.offsets,
+        con.def.page_hits,
+        con.def.page_colors,
+        con.def.page_at_lstart,
+    ) = saved;
+    if limited {
+        cout_dline(con, "PPLIMIT-REACHED");
+    }
+    con.def.muted.set(muted);
+}
+
+//note streaming CSV tokenizer, a quote only opens a quoted field at its start and two quotes inside a quoted field are an escaped quote
+#[derive(Copy, Clone, Debug)]
+struct CsvTok {
+    in_quote: bool,
+    field_start: bool,
+    after_quote: bool, //note a quoted field has just been closed, another quote reopens it
+}
+impl CsvTok {
+    fn new() -> Self {
+        Self {
+            in_quote: false,
+            field_start: true,
+            after_quote: false,
+        }
+    }
+    //note returns the byte if it ends a field (delimiter or newline)
+    fn step(&mut self, byt: u8, delim: u8) -> Option<u8> {
+        if self.in_quote {
+            if byt == b'"' {
+                self.in_quote = false;
+                self.after_quote = true;
+            }
+            return None;
+        }
+        let quote_ok = self.field_start || self.after_quote;
+        self.after_quote = false;
+        self.field_start = byt == delim || byt == b'\n';
+        if byt == b'"' && quote_ok {
+            self.in_quote = true;
+            return None;
+        }
+        if self.field_start {
+            Some(byt)
+        } else {
+            None
+        }
+    }
+}
+
+//note feeds start and end (excluding the byte ending it) of each field from `from` (which must be a field start) to f until f returns false (then returns true), the byte ending a field is 0 at end of file
+fn csv_walk(
+    con: &mut Ctx,
+    from: u64,
+    prog: &mut SearchProgress,
+    mut f: impl FnMut(u64, u64, u8) -> bool,
+) -> CustRes<bool> {
+    let delim = con.def.csv.unwrap_or(b',');
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let mut tok = CsvTok::new();
+    let mut fstart = from;
+    let mut after_delim = false;
+    let stopped = walk_chars(con, from, prog, |pos, chr| match tok.step(chr[0], delim) {
+        None => true,
+        Some(ender) => {
+            let go = f(fstart, pos, ender);
+            fstart = pos + 1;
+            after_delim = ender == delim;
+            go
+        }
+    })?;
+    if stopped || INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        return Ok(stopped);
+    }
+    if fstart < file_len || after_delim {
+        return Ok(!f(fstart, file_len, 0));
+    }
+    Ok(false)
+}
+
+//note returns starts and column numbers (1-based) of at most keep fields of the record containing off, the last one is the field containing off, scanning from start of real line (so a quoted field containing newlines confuses it)
+fn csv_fields_upto(
+    con: &mut Ctx,
+    off: u64,
+    keep: usize,
+    prog: &mut SearchProgress,
+) -> CustRes<collections::VecDeque<(u64, usize)>> {
+    let lstart = line_start_before(con, off)?;
+    let mut retval = collections::VecDeque::from([(lstart, 1)]);
+    let mut col = 1;
+    csv_walk(con, lstart, prog, |_, end, ender| {
+        if end >= off || ender == 0 {
+            return false;
+        }
+        col = if ender == b'\n' { 1 } else { col + 1 };
+        retval.push_back((end + 1, col));
+        if retval.len() > keep {
+            retval.pop_front();
+        }
+        true
+    })?;
+    Ok(retval)
+}
+
+//note header names are taken from the first record, only the head of each name is decoded
+fn load_csv_header(con: &mut Ctx, prog: &mut SearchProgress) -> CustRes<()> {
+    use std::io::*;
+    let bom_end = con.def.bom_end;
+    let delim = con.def.csv.unwrap_or(b',');
+    let mut spans = vec![];
+    csv_walk(con, bom_end, prog, |start, end, ender| {
+        spans.push((start, end));
+        ender == delim && end - bom_end < CSV_HEADER_LIMIT
+    })?;
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut names = vec![];
+    for (start, end) in spans {
+        let mut buf = vec![0; cmp::min(end - start, CSV_NAME_LIMIT) as usize];
+        fil.seek(io::SeekFrom::Start(start))?;
+        fil.read_exact(&mut buf)?;
+        let full = buf.len() as u64 == end - start;
+        let unquoted = csv_unquote(&buf, full);
+        let name = con.enc.decode_without_bom_handling(&unquoted).0;
+        names.push(name.trim().to_owned());
+    }
+    con.def.csv_header = Some(names);
+    Ok(())
+}
+
+//note strips quotes of a quoted field and unescapes doubled quotes, full is false if the field is cut
+fn csv_unquote(field: &[u8], full: bool) -> Vec<u8> {
+    let mut field = field;
+    if full && field.last() == Some(&b'\r') {
+        field = &field[..field.len() - 1];
+    }
+    if field.first() != Some(&b'"') {
+        return field.to_vec();
+    }
+    field = &field[1..];
+    if full && field.last() == Some(&b'"') {
+        field = &field[..field.len() - 1];
+    }
+    let mut retval = Vec::with_capacity(field.len());
+    let mut idx = 0;
+    while idx < field.len() {
+        retval.push(field[idx]);
+        idx += if field[idx..].starts_with(b"\"\"") {
+            2
+        } else {
+            1
+        };
+    }
+    retval
+}
+
+//note one field per display line: column number, header name and head of the value
+fn csv_dline(con: &mut Ctx, start: u64, end: u64, col: usize) -> CustRes<String> {
+    use std::io::*;
+    let dlwidth = text_width(con);
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut buf = vec![0; cmp::min(end - start, (dlwidth * 4 + 2) as u64) as usize];
+    fil.seek(io::SeekFrom::Start(start))?;
+    fil.read_exact(&mut buf)?;
+    let cont = csv_unquote(&buf, buf.len() as u64 == end - start);
+    let name = con
+        .def
+        .csv_header
+        .as_ref()
+        .and_then(|names| names.get(col - 1))
+        .map_or("", |name| name.as_str());
+    let mut lin = format!("{}{} {}: ", if col == 1 { "$" } else { " " }, col, name);
+    let mut lin_width = disp_width(con, &lin);
+    let tr = con.tr.clone();
+    for (_, cstr) in tr.buf2strs(&cont, cont.len(), con, true) {
+        let cstr = match cstr.as_str() {
+            "\n" => "\\n".to_owned(),
+            "\t" => " ".to_owned(),
+            _ => cstr,
+        };
+        let cell_width = disp_width(con, &cstr);
+        if lin_width + cell_width > dlwidth {
+            break;
+        }
+        lin += &cstr;
+        lin_width += cell_width;
+    }
+    let nl_before = if con.def.nu && col == 1 {
+        nl_count_before(con, start)?
+    } else {
+        None
+    };
+    Ok(gutter(con, col == 1, nl_before, start) + &lin)
+}
+
+//note renders fields from the field containing bookmark, one per display line
+fn show_csv_page(con: &mut Ctx) -> CustRes<()> {
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    if con.def.bookmark < con.def.bom_end {
+        con.def.bookmark = con.def.bom_end;
+    }
+    let mut prog = SearchProgress::new(file_len);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    if con.def.csv_header.is_none() {
+        load_csv_header(con, &mut prog)?;
+    }
+    let (start, mut col) = match con.def.csv_hint {
+        Some(inner) if inner.0 == con.def.bookmark => inner,
+        _ => *csv_fields_upto(con, con.def.bookmark, 1, &mut prog)?
+            .back()
+            .unwrap(),
+    };
+    con.def.bookmark = start;
+    let mut fields = vec![];
+    let mut pos = file_len;
+    let dheight = con.def_dheight;
+    let stopped = csv_walk(con, start, &mut prog, |fstart, fend, ender| {
+        fields.push((fstart, fend, col));
+        col = if ender == b'\n' { 1 } else { col + 1 };
+        pos = cmp::min(fend + 1, file_len);
+        fields.len() < dheight
+    })?;
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    for (fstart, fend, fcol) in fields {
+        let lin = csv_dline(con, fstart, fend, fcol)?;
+        cout_dline(con, &lin);
+    }
+    if !stopped || pos == file_len {
+        cout_dline(con, "END-OF-FILE");
+        pos = file_len;
+    }
+    con.def.bookmark_end = pos;
+    con.def.csv_hint = Some((pos, col));
+    cout_status_if_needed(con);
     Ok(())
 }
 
-fn percentage_wise(con: &mut Ctx) -> CustRes<()> {
-    let digits = subsli_cut_rear!(con.def.iline, 1);
-    let perc = match digits.parse::<f64>() {
-        Err(_) => {
-            coutln!("Percentage invalid input.");
+//note collects fields before bookmark (going back into previous records if needed) until the page is full
+fn show_csv_prev_page(con: &mut Ctx) -> CustRes<()> {
+    let bom_end = con.def.bom_end;
+    let mut prog = SearchProgress::new(con.def.bookmark);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let dheight = con.def_dheight;
+    let mut fields = csv_fields_upto(con, con.def.bookmark, dheight + 1, &mut prog)?;
+    let mut first = fields.pop_back().unwrap();
+    if let Some(inner) = fields.front() {
+        first = *inner;
+    }
+    while fields.len() < dheight && first.1 == 1 && first.0 > bom_end {
+        let more = csv_fields_upto(con, first.0 - 1, dheight - fields.len(), &mut prog)?;
+        for field in more.into_iter().rev() {
+            fields.push_front(field);
+        }
+        first = *fields.front().unwrap();
+    }
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
+        return Ok(());
+    }
+    match fields.front() {
+        None => {
+            info!("{}", "Top of file reached.");
+            Ok(())
+        }
+        Some(inner) => {
+            con.def.bookmark = inner.0;
+            con.def.csv_hint = Some(*inner);
+            show_csv_page(con)
+        }
+    }
+}
+
+//note c {number} jumps to the column of the current record
+fn cmd_c(con: &mut Ctx) -> CustRes<()> {
+    if con.def.csv.is_none() {
+        coutln!("CSV mode is off.");
+        return Ok(());
+    }
+    let target = match con.def.iline[2..].trim().parse::<usize>() {
+        Ok(inner) if inner > 0 => inner,
+        _ => {
+            coutln!("Column number invalid.");
             return Ok(());
         }
-        Ok(inner) => inner / 100.0,
     };
-    let chk_floating_point = perc >= 0f64 && perc <= 1.0; //note this handles NaN etc.
-    if !chk_floating_point {
-        coutln!("Percentage invalid number.");
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let mut prog = SearchProgress::new(file_len);
+    catch_sigint(true);
+    defer! {
+        catch_sigint(false);
+    }
+    let off = cmp::max(con.def.bookmark, con.def.bom_end);
+    let lstart = line_start_before(con, off)?;
+    let mut col = 1;
+    let mut found = None;
+    csv_walk(con, lstart, &mut prog, |fstart, _, ender| {
+        if col == target {
+            found = Some(fstart);
+            return false;
+        }
+        col += 1;
+        ender != b'\n' && ender != 0
+    })?;
+    if INTERRUPTED.load(sync::atomic::Ordering::Relaxed) {
+        coutln!("Search interrupted.");
         return Ok(());
     }
-    let file_len = con.def.fsmd.as_ref().unwrap().len();
-    //"Casting from an integer to float will produce the closest possible float"
-    //"Casting from a float to an integer will round the float towards zero"
-    let mut bm = (file_len as f64 * perc) as u64;
-    if bm > file_len {
-        bm = file_len;
+    match found {
+        None => {
+            coutln!(format!("Record has only {} columns.", col - 1));
+        }
+        Some(inner) => {
+            con.def.bookmark = inner;
+            con.def.bookmark_end = inner;
+            con.def.csv_hint = Some((inner, target));
+            show_page(con)?;
+        }
     }
-    con.def.bookmark = bm;
-    con.def.bookmark_end = bm;
-    show_page(con)?;
     Ok(())
 }
 
-fn cmd_g(con: &Ctx) {
-    coutln!(con.def.text_file_path_str);
+//note date and time separated by T or space, fraction of second separated by . or comma, timezone suffix is ignored
+fn parse_ts(txt: &str) -> Option<chrono::NaiveDateTime> {
+    let txt = txt.replacen(' ', "T", 1).replace(',', ".");
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(inner) = chrono::NaiveDateTime::parse_from_str(&txt, fmt) {
+            return Some(inner);
+        }
+    }
+    let date = chrono::NaiveDate::parse_from_str(&txt, "%Y-%m-%d").ok()?;
+    date.and_hms_opt(0, 0, 0)
+}
+
+//note returns start of the first real line at or after off which contains a timestamp (found within TS_PROBE_LEN bytes) and the timestamp
+fn first_ts_after(
+    con: &mut Ctx,
+    re: &regex::bytes::Regex,
+    off: u64,
+) -> CustRes<Option<(u64, chrono::NaiveDateTime)>> {
+    use std::io::*;
+    let lstart = match probe_line_start(con, off)? {
+        None => {
+            return Ok(None);
+        }
+        Some(inner) => inner,
+    };
+    let fil = con.def.fsfile.as_mut().unwrap();
+    fil.seek(io::SeekFrom::Start(lstart))?;
+    let mut buf = vec![0; TS_PROBE_LEN];
+    let rlen = read_to_buf(fil, &mut buf)?;
+    for found in re.find_iter(&buf[..rlen]) {
+        let ts = match str::from_utf8(found.as_bytes()).ok().and_then(parse_ts) {
+            None => {
+                continue;
+            }
+            Some(inner) => inner,
+        };
+        let head = &buf[..found.start()];
+        let start = memchr::memrchr(b'\n', head).map_or(0, |pos| pos + 1);
+        return Ok(Some((lstart + start as u64, ts)));
+    }
+    Ok(None)
+}
+
+//note resyncs a probe to start of the first real line at or after off, None if there is no such line
+fn probe_line_start(con: &mut Ctx, off: u64) -> CustRes<Option<u64>> {
     let file_len = con.def.fsmd.as_ref().unwrap().len();
-    let perc = con.def.bookmark * 100 / file_len;
-    println!("{}{}", perc, "%");
-    println!("{}{}{}", con.def.bookmark, "/", file_len);
+    let lstart = if at_line_start(con, off)? {
+        Some(off)
+    } else {
+        next_line_start(con, off)?
+    };
+    Ok(lstart.filter(|inner| *inner < file_len))
 }
 
-fn search_bytes(con: &mut Ctx) -> CustRes<()> {
-    //fixme this method is safe for UTF8 but not safe for some other encoding schemes (search raw bytes might end up in the middle of a multi-byte code point)
+//note returns offset after the first newline at or after off, None if there is no newline
+fn next_line_start(con: &mut Ctx, off: u64) -> CustRes<Option<u64>> {
     use std::io::*;
-    debug_assert!(con.def.iline.len() != 0);
-    if con.def.iline.len() == 1 {
-        info!("{}", "Cannot search without input");
-        return Ok(());
+    let file_len = con.def.fsmd.as_ref().unwrap().len();
+    let fil = con.def.fsfile.as_mut().unwrap();
+    let mut pos = off;
+    while pos < file_len {
+        let hi = cmp::min(file_len, pos + SEARCH_CHUNK_LEN as u64);
+        let mut buf = vec![0; (hi - pos) as usize];
+        fil.seek(io::SeekFrom::Start(pos))?;
+        fil.read_exact(&mut buf)?;
+        if let Some(idx) = memchr::memchr(b'\n', &buf) {
+            return Ok(Some(pos + idx as u64 + 1));
+        }
+        pos = hi;
+    }
+    Ok(None)
+}
+
+//note t {timestamp} binary-searches a time-ordered log by byte offset and lands on the first record at or after the time
+fn cmd_t(con: &mut Ctx) -> CustRes<()> {
+    let target = match parse_ts(con.def.iline[2..].trim()) {
+        None => {
+            coutln!("Timestamp invalid.");
+            return Ok(());
+        }
+        Some(inner) => inner,
+    };
+    let re = regex::bytes::Regex::new(TS_REGEX)?;
+    let mut lo = con.def.bom_end;
+    let mut hi = con.def.fsmd.as_ref().unwrap().len();
+    //note records are assumed sorted, so whether the first record at or after an offset is late enough is monotone
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match first_ts_after(con, &re, mid)? {
+            Some((_, ts)) if ts < target => {
+                lo = mid + 1;
+            }
+            _ => {
+                hi = mid;
+            }
+        }
+    }
+    match first_ts_after(con, &re, lo)? {
+        None => {
+            info!("{}", "Not found.");
+        }
+        Some((start, _)) => {
+            con.def.bookmark = start;
+            con.def.bookmark_end = start;
+            show_page(con)?;
+        }
+    }
+    Ok(())
+}
+
+//note returns start of the first real line at or after off and head of its key (the whole line, or a field if bs_field is set)
+fn bs_key_after(con: &mut Ctx, off: u64) -> CustRes<Option<(u64, Vec<u8>)>> {
+    use std::io::*;
+    let lstart = match probe_line_start(con, off)? {
+        None => {
+            return Ok(None);
+        }
+        Some(inner) => inner,
+    };
+    let fil = con.def.fsfile.as_mut().unwrap();
+    fil.seek(io::SeekFrom::Start(lstart))?;
+    let mut buf = vec![0; BS_PROBE_LEN];
+    let rlen = read_to_buf(fil, &mut buf)?;
+    let mut lin = &buf[..rlen];
+    if let Some(pos) = memchr::memchr(b'\n', lin) {
+        lin = &lin[..pos];
+    }
+    if let Some(inner) = lin.strip_suffix(b"\r") {
+        lin = inner;
     }
-    let (blob, _enc, res) = con.enc.encode(&con.def.iline[1..]);
+    let key = if con.def.bs_field == 0 {
+        lin
+    } else {
+        lin.split(|byt| *byt == con.def.bs_delim)
+            .nth(con.def.bs_field - 1)
+            .unwrap_or(b"")
+    };
+    Ok(Some((lstart, key.to_vec())))
+}
+
+//note bs {key} binary-searches a lexicographically (bytewise) sorted file and lands on the first line whose key is not less than the given key
+fn cmd_bs(con: &mut Ctx) -> CustRes<()> {
+    let (key, _, res) = con.enc.encode(con.def.iline[3..].trim());
     if res {
         info!("{}", "Unmappable characters in input");
         return Ok(());
     }
-    let fil = con.def.fsfile.as_mut().unwrap();
-    fil.seek(io::SeekFrom::Start(con.def.bookmark))?;
-    let mut buf = vec![0; cmp::max(blob.len() * 0x100, 0x10000)];
-    let idx = match read_n_find(fil, &mut buf, &blob)? {
-        None => {
+    let key = key.into_owned();
+    if key.is_empty() {
+        coutln!("Key is empty.");
+        return Ok(());
+    }
+    let mut lo = con.def.bom_end;
+    let mut hi = con.def.fsmd.as_ref().unwrap().len();
+    //note lines are assumed sorted, so whether the first line at or after an offset is not less than the key is monotone
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match bs_key_after(con, mid)? {
+            Some((_, lkey)) if lkey < key => {
+                lo = mid + 1;
+            }
+            _ => {
+                hi = mid;
+            }
+        }
+    }
+    match bs_key_after(con, lo)? {
+        Some((start, lkey)) if lkey.starts_with(&key) => {
+            con.def.bookmark = start;
+            con.def.bookmark_end = start;
+            show_page(con)?;
+        }
+        _ => {
             info!("{}", "Not found.");
-            return Ok(());
         }
-        Some(inner) => inner as u64,
-    };
-    con.def.bookmark += idx;
-    con.def.bookmark_end = con.def.bookmark;
-    show_page(con)?;
+    }
     Ok(())
 }
 
 fn open_text(con: &mut Ctx, filenm: &str) -> CustRes<()> {
     hash_fpath!(con, filenm);
     con.text_file_bookmark_path = con.bookmark_dir.join(&con.def.text_file_path_hash);
+    let mut rec = None;
     if con.text_file_bookmark_path.try_exists()? {
         if !real_reg_file_without_symlink(&con.def.text_file_bookmark_path) {
             return Err("Caret file is not regular file".into());
         }
-        con.bookmark = fs::read_to_string(&con.def.text_file_bookmark_path)?.parse()?;
+        let (off, inner) = parse_bookmark(&fs::read_to_string(&con.def.text_file_bookmark_path)?)?;
+        con.bookmark = off;
+        rec = inner;
         info!("{}", "BOOKMARK found.");
     }
     let mut ok: bool = false;
@@ -964,12 +4898,34 @@ fn open_text(con: &mut Ctx, filenm: &str) -> CustRes<()> {
     }
     let fil = fs::File::open(&con.def.text_file_path)?;
     let md = fil.metadata()?;
+    let drifted = rec.as_ref().is_some_and(|inner: &BookmarkRec| {
+        inner.size != md.len() || inner.mtime != mtime_nanos(&md)
+    });
+    con.def.fsmd = Some(md);
+    con.def.fsfile = Some(fil);
+    if drifted {
+        let rec = rec.unwrap();
+        let (_, ctx) = bookmark_ctx(con, con.def.bookmark, con.def.fsmd.as_ref().unwrap().len())?;
+        if short_hash(con, &ctx) != rec.hash {
+            match relocate_bookmark(con, con.def.bookmark, &rec)? {
+                None => {
+                    info!("{}", "BOOKMARK context not found, file changed.");
+                }
+                Some(inner) => {
+                    con.def.bookmark = inner;
+                    info!("{}", "BOOKMARK relocated.");
+                }
+            }
+        }
+    }
     //note you must allow md.len() == con.def.bookmark, because when you open empty file this happens naturally
-    if md.len() < con.def.bookmark {
+    if con.def.fsmd.as_ref().unwrap().len() < con.def.bookmark {
         con.def.bookmark = 0; //todo better handling?
     }
-    con.def.fsmd = Some(md);
-    con.def.fsfile = Some(fil);
+    con.def.line_anchors.clear();
+    con.def.checkpoints.clear();
+    con.def.csv_header = None;
+    con.def.csv_hint = None;
     let tr = con.tr.clone();
     con.def.bom_end = tr.chk_bom(con)?;
     show_page(con)?;
@@ -1074,9 +5030,12 @@ struct CtxDef {
     stdin_w: StdinWrapper,
     def_dlwidth: usize,
     def_dheight: usize,
+    dlwidth_pinned: bool, //note set by env var so terminal size is ignored
+    dheight_pinned: bool,
     def_enc_scheme: String,
     def_wind_size: usize,
     def_cache_size: usize,
+    tabstop: usize,
     home_dir: PathBuf,
     everycom: PathBuf,
     app_support_dir: PathBuf,
@@ -1091,11 +5050,63 @@ struct CtxDef {
     bookmark_end: u64,
     bom_end: u64,
     iline: String,
+    muted: cell::Cell<bool>,
+    dline_sink: Option<cell::RefCell<Vec<String>>>, //note display lines are collected here instead of stdout if set
+    in_tui: bool,
     reversed: bool,
-    //show_line_number: bool,//todo
+    status: bool, //note print status line after each page
+    view_mode: ViewMode,
+    ctrl_policy: CtrlPolicy,
+    grapheme: bool,         //note display lines never break inside a grapheme cluster
+    ambiwidth_double: bool, //note East Asian Ambiguous chars (e.g. Cyrillic, Greek in CJK fonts) take 2 columns
+    nu: bool,               //note show line number
+    line_anchors: collections::BTreeMap<u64, u64>, //note offset => count of newlines before it
+    checkpoints: collections::BTreeMap<u64, Checkpoint>, //note offset => JSON or XML tokenizer state at it
+    page_nl: Option<u64>, //note count of newlines before the page being rendered (before bookmark for show_page, before bookmark_end for show_prev_page)
+    page_at_lstart: bool, //note bookmark is at start of real line
+    offsets: Option<OffsetRadix>, //note show offset of first char of each display line
+    page_base: u64,       //note offset of first byte of buffer rendered by render_p
+    wrapscan: bool,       //note search wraps around the end (or the start) of file
+    hl: bool,             //note highlight matches of last search pattern
+    ignorecase: bool,
+    smartcase: bool, //note case-sensitive if pattern contains uppercase char
+    wholeword: bool,
+    search_pat: SearchPat,     //note last search pattern
+    filter: Option<SearchPat>, //note only lines containing matches are shown
+    json_pp: bool,             //note show re-indented JSON after jm/jn/ju
+    pp_limit: usize,
+    bs_field: usize, //note 1-based field compared by bs, 0 for whole line
+    bs_delim: u8,
+    csv: Option<u8>, //note delimiter of CSV mode, which shows one field per display line
+    csv_header: Option<Vec<String>>,
+    csv_hint: Option<(u64, usize)>, //note a field start with known column number, saves scanning from start of record
+    loghl: bool, //note color log levels, timestamps etc. by rules from LOG_RULES_FILENM
+    log_rules: Vec<(regex::bytes::Regex, String)>, //note regex and SGR code
+    page_colors: Vec<(u64, u64, usize)>, //note start, end and rule index of colored spans in the page being rendered
+    page_hits: Vec<(u64, u64)>, //note offsets and lengths of matches of search_pat in the page being rendered
     fsfile: Option<fs::File>,
     fsmd: Option<fs::Metadata>,
 }
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+enum ViewMode {
+    #[default]
+    Text,
+    Hex,
+    Mixed, //note text with invalid byte sequences displayed as \xNN
+}
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+enum CtrlPolicy {
+    #[default]
+    Caret, //note e.g. ^[ for ESC
+    Hex,   //note e.g. \x1B for ESC
+    Glyph, //note control pictures, e.g. U+241B for ESC, and U+FFFD for invalid bytes
+    Raw, //note legacy behavior, control chars are written to terminal and invalid bytes are skipped
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum OffsetRadix {
+    Dec,
+    Hex,
+}
 struct StdinWrapper {
     lines: std::io::Lines<std::io::StdinLock<'static>>,
 }
@@ -1265,3 +5276,827 @@ fn init_sqlite(conn: &rusqlite::Connection) -> Result<(), CustomErr> {
     conn.execute("CREATE INDEX idx_fullpath ON files (fullpath)", ())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Digest;
+
+    #[test]
+    fn tui_keys_in_one_read() {
+        let mut pending = b"\x1b[6~\x1b[6~jq\x1b[".to_vec();
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::PgDn));
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::PgDn));
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::Chr(b'j')));
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::Chr(b'q')));
+        assert_eq!(tui_parse_key(&mut pending), None);
+        pending.extend_from_slice(b"2;5A\x1bx");
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::Other));
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::Esc));
+        assert_eq!(tui_parse_key(&mut pending), Some(TuiKey::Chr(b'x')));
+        assert!(pending.is_empty());
+    }
+
+    fn pat_of(blob: &[u8]) -> SearchPat {
+        SearchPat {
+            blob: blob.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlapping_candidate_after_false_positive() {
+        //note 81 41 is one GB18030 char, so the first AA is rejected but the one overlapping it is real
+        let cont = b"\x81\x41\x41\x41";
+        let pat = pat_of(b"AA");
+        let tr = GB18030Rdr {};
+        let fwd: Vec<_> = find_iter_in(&tr, &pat, cont, 0..cont.len(), false, Some(0)).collect();
+        assert_eq!(fwd, vec![(2, 2)]);
+        let rev: Vec<_> = find_iter_in(&tr, &pat, cont, 0..cont.len(), true, Some(0)).collect();
+        assert_eq!(rev, vec![(2, 2)]);
+        let mut upper = pat_of(b"aa");
+        upper.fold_mask = vec![true; 2];
+        assert_eq!(
+            find_in(&tr, &upper, cont, 0..cont.len(), false, Some(0)),
+            Some((2, 2))
+        );
+    }
+
+    #[test]
+    fn false_positive_in_two_byte_char() {
+        //note 81 41 is one GB18030 char, A inside it is not a match
+        let cont = b"\x81\x41\x82\x41";
+        let tr = GB18030Rdr {};
+        let hits: Vec<_> =
+            find_iter_in(&tr, &pat_of(b"A"), cont, 0..cont.len(), false, Some(0)).collect();
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn false_positive_in_four_byte_char() {
+        //note 81 30 81 30 is one GB18030 char, 0 inside it is not a match
+        let cont = b"x\x81\x30\x81\x30";
+        let tr = GB18030Rdr {};
+        let hits: Vec<_> =
+            find_iter_in(&tr, &pat_of(b"0"), cont, 0..cont.len(), false, Some(0)).collect();
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn real_hit_after_multi_byte_char() {
+        let cont = b"\x81\x41A\x81\x30\x81\x300\x81\x41A";
+        let tr = GB18030Rdr {};
+        let hits: Vec<_> =
+            find_iter_in(&tr, &pat_of(b"A"), cont, 0..cont.len(), false, Some(0)).collect();
+        assert_eq!(hits, vec![(2, 1), (10, 1)]);
+        let hits: Vec<_> =
+            find_iter_in(&tr, &pat_of(b"0"), cont, 0..cont.len(), false, Some(0)).collect();
+        assert_eq!(hits, vec![(7, 1)]);
+    }
+
+    //note Ctx locks stdin, so tests creating one take turns
+    static CTX_LOCK: sync::Mutex<()> = sync::Mutex::new(());
+
+    //note only one Ctx may be alive at a time, each holds the stdin lock
+    fn ctx_of(fpath: &path::Path) -> Ctx {
+        let mut con = Ctx {
+            hasher: sha2::Sha256::new(),
+            args: vec![],
+            tr: Box::new(UTF8Rdr {}),
+            enc: encoding_rs::UTF_8,
+            def: CtxDef::default(),
+        };
+        con.def.text_file_path = fpath.to_path_buf();
+        con.def.fsfile = Some(fs::File::open(fpath).unwrap());
+        con.def.fsmd = Some(fs::metadata(fpath).unwrap());
+        con.def_dlwidth = DEF_DISPLAY_LINE_WIDTH;
+        con.def_dheight = DEF_DISPLAY_HEIGHT;
+        con.tabstop = DEF_TABSTOP;
+        con.pp_limit = DEF_PP_LIMIT;
+        con.def.dline_sink = Some(Default::default());
+        con
+    }
+
+    fn take_dlines(con: &Ctx) -> Vec<String> {
+        con.def.dline_sink.as_ref().unwrap().take()
+    }
+
+    #[test]
+    fn count_overlap_at_chunk_edge() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        //note aaa straddles the edge of first chunk, aa at its start and aa right after overlap
+        let mut blob = vec![b'b'; SEARCH_CHUNK_LEN - 1];
+        blob.extend_from_slice(b"aaa\nbaab\n");
+        let fpath = env::temp_dir().join(format!("gmtc_count_{}.txt", process::id()));
+        fs::write(&fpath, &blob).unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut prog = SearchProgress::new(blob.len() as u64);
+        let counted = count_matches(&mut con, &pat_of(b"aa"), &mut prog);
+        fs::remove_file(&fpath).unwrap();
+        assert_eq!(counted.unwrap(), (2, 2));
+    }
+
+    #[test]
+    fn pp_after_search_hit() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_pp_{}.json", process::id()));
+        fs::write(&fpath, b"{\"foo\":[1,2],\"bar\":\"foo\"}\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.hl = true;
+        con.def.search_pat = pat_of(b"foo");
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        assert!(page.iter().any(|lin| lin.contains("\x1b[7m")));
+        con.def.iline = "pp json".to_owned();
+        let res = res.and_then(|_| cmd_pp(&mut con));
+        let pretty = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert!(pretty.iter().any(|lin| lin.contains("\"bar\": \"foo\"")));
+        assert!(pretty.iter().all(|lin| !lin.contains("\x1b[7m")));
+        assert_eq!(con.def.page_hits.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_output_without_colors() {
+        assert_eq!(
+            strip_sgr("[\x1b[32mINFO \x1b[0m gmtc] Not found."),
+            "[INFO  gmtc] Not found."
+        );
+    }
+
+    #[test]
+    fn whole_word_after_ascii_like_trailing_byte() {
+        //note 81 40 is one GB18030 char ending in @, so the match right after it is inside a word
+        let cont = b"\x81\x40ab ab\x81\x5B";
+        let mut pat = pat_of(b"ab");
+        pat.word = true;
+        let tr = GB18030Rdr {};
+        let hits: Vec<_> = find_iter_in(&tr, &pat, cont, 0..cont.len(), false, Some(0)).collect();
+        assert!(hits.is_empty());
+        let cont = b"\x81\x40 ab \x81\x5B\x81\x5Bab";
+        let hits: Vec<_> = find_iter_in(&tr, &pat, cont, 0..cont.len(), false, Some(0)).collect();
+        assert_eq!(hits, vec![(3, 2)]);
+        //note match ending in such a char is followed by a word char
+        let mut pat = pat_of(b"\x81\x40");
+        pat.word = true;
+        let cont = b"\x81\x40a \x81\x40";
+        let hits: Vec<_> = find_iter_in(&tr, &pat, cont, 0..cont.len(), false, Some(0)).collect();
+        assert_eq!(hits, vec![(4, 2)]);
+    }
+
+    fn folded_pat_of(ptn: &str) -> SearchPat {
+        SearchPat {
+            blob: ptn.as_bytes().to_vec(),
+            fold_mask: ptn
+                .chars()
+                .flat_map(|chr| iter::repeat_n(chr.is_ascii(), chr.len_utf8()))
+                .collect(),
+            folded: ptn.chars().map(fold_chr).collect(),
+            utf8: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unicode_simple_case_folding() {
+        let cont = "ΟΔΟΣ οδος ΟΔΟΣ, STRAẞE straße".as_bytes();
+        let tr = UTF8Rdr {};
+        let starts = |ptn: &str| -> Vec<usize> {
+            find_iter_in(
+                &tr,
+                &folded_pat_of(ptn),
+                cont,
+                0..cont.len(),
+                false,
+                Some(0),
+            )
+            .map(|(idx, _)| idx)
+            .collect()
+        };
+        assert_eq!(starts("οδοσ"), vec![0, 9, 18]);
+        assert_eq!(starts("ΟΔΟς,"), vec![18]);
+        assert_eq!(starts("straße"), vec![28, 37]);
+        //note simple folding does not turn sharp s into ss
+        assert!(starts("strasse").is_empty());
+    }
+
+    #[test]
+    fn filter_keeps_last_search_pattern() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_filter_{}.txt", process::id()));
+        fs::write(&fpath, b"foo 1\nbar 2\nfoo bar 3\nbaz 4\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.iline = "/foo".to_owned();
+        let mut res = search_bytes(&mut con);
+        take_dlines(&con);
+        con.def.iline = "&bar".to_owned();
+        res = res.and_then(|_| cmd_filter(&mut con));
+        let page = take_dlines(&con);
+        con.def.iline = "count /baz".to_owned();
+        res = res.and_then(|_| cmd_count(&mut con));
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(con.def.search_pat.blob, b"foo");
+        assert_eq!(con.def.filter.as_ref().unwrap().blob, b"bar");
+        assert!(page[0].contains("bar 2"));
+        assert!(page[1].contains("foo bar 3"));
+    }
+
+    #[test]
+    fn tab_after_full_display_line() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_tab_{}.txt", process::id()));
+        fs::write(&fpath, b"aaaaaaaaaaaaaaaaaaa\tx\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = 20;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        con.tr = Box::new(GB18030Rdr {});
+        let res = res.and_then(|_| show_page(&mut con));
+        let gb_page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(page[0], format!(" {}", "a".repeat(19)));
+        assert_eq!(page[1], format!(" {}x", " ".repeat(DEF_TABSTOP)));
+        assert_eq!(page, gb_page);
+    }
+
+    #[test]
+    fn json_checkpoint_between_commands() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_jcp_{}.txt", process::id()));
+        let cont = b"{\"x\":0}\n{\"a\":[1,{\"b\":\"]\"}],\"c\":2}\n";
+        fs::write(&fpath, cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut prog = SearchProgress::new(cont.len() as u64);
+        let mut walk = || -> CustRes<_> {
+            con.def.bookmark = 13;
+            con.def.iline = "jm".to_owned();
+            cmd_json(&mut con)?;
+            let after_jm = con.def.bookmark;
+            con.def.iline = "ju".to_owned();
+            cmd_json(&mut con)?;
+            let after_ju = con.def.bookmark;
+            let seen = con.def.checkpoints.keys().copied().collect::<Vec<_>>();
+            let (cached, _) = json_state_at(&mut con, 20, &mut prog)?;
+            con.def.checkpoints.clear();
+            let (fresh, _) = json_state_at(&mut con, 20, &mut prog)?;
+            Ok((after_jm, after_ju, seen, cached, fresh))
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        let (after_jm, after_ju, seen, cached, fresh) = res.unwrap();
+        assert_eq!(after_jm, 25);
+        assert_eq!(after_ju, 8);
+        assert_eq!(seen, vec![13, 25]);
+        assert_eq!(cached, vec![8, 13, 16]);
+        assert_eq!(cached, fresh);
+    }
+
+    #[test]
+    fn mixed_truncated_char_at_eof() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_cut_{}.txt", process::id()));
+        let mut pages = vec![];
+        let mut walk = || -> CustRes<()> {
+            for (cont, gb) in [(&b"abc\xE4\xB8"[..], false), (&b"abc\x81\x30"[..], true)] {
+                fs::write(&fpath, cont)?;
+                let mut con = ctx_of(&fpath);
+                con.def.view_mode = ViewMode::Mixed;
+                if gb {
+                    con.tr = Box::new(GB18030Rdr {});
+                }
+                show_page(&mut con)?;
+                pages.push(take_dlines(&con));
+                con.def.grapheme = true;
+                show_page(&mut con)?;
+                pages.push(take_dlines(&con));
+            }
+            Ok(())
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(pages[0], vec![" abc\\xE4\\xB8", "END-OF-FILE"]);
+        assert_eq!(pages[1], pages[0]);
+        assert_eq!(pages[2], vec![" abc\\x81\\x30", "END-OF-FILE"]);
+        assert_eq!(pages[3], pages[2]);
+    }
+
+    #[test]
+    fn hex_view_rows() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_hex_{}.txt", process::id()));
+        fs::write(&fpath, b"ab\x00\xFFcd").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = 40;
+        con.def.view_mode = ViewMode::Hex;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(
+            page,
+            vec![
+                "00000000  61 62 00 ff  ab..",
+                "00000004  63 64        cd",
+                "END-OF-FILE"
+            ]
+        );
+        assert_eq!(con.def.bookmark_end, 6);
+    }
+
+    #[test]
+    fn ctrl_and_invalid_bytes_by_policy() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_ctrl_{}.txt", process::id()));
+        fs::write(&fpath, b"a\x01b\x7F\xFFc\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut lins = vec![];
+        let mut res = Ok(());
+        for policy in [
+            CtrlPolicy::Caret,
+            CtrlPolicy::Hex,
+            CtrlPolicy::Glyph,
+            CtrlPolicy::Raw,
+        ] {
+            con.def.ctrl_policy = policy;
+            res = res.and_then(|_| show_page(&mut con));
+            lins.push(take_dlines(&con).remove(0));
+        }
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(lins[0], " a^Ab^?\u{FFFD}c");
+        assert_eq!(lins[1], " a\\x01b\\x7F\\xFFc");
+        assert_eq!(lins[2], " a\u{2401}b\u{2421}\u{FFFD}c");
+        assert_eq!(lins[3], " a\u{1}b\u{7F}c");
+    }
+
+    #[test]
+    fn wide_and_zero_width_chars() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_width_{}.txt", process::id()));
+        fs::write(&fpath, "中文字e\u{301}中\n".as_bytes()).unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = MIN_TEXT_WIDTH;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(disp_width(&con, "中"), 2);
+        assert_eq!(disp_width(&con, "e\u{301}"), 1);
+        assert_eq!(disp_width(&con, "①"), 1);
+        con.def.ambiwidth_double = true;
+        assert_eq!(disp_width(&con, "①"), 2);
+        assert_eq!(page[0], " 中文字e\u{301}");
+        assert_eq!(page[1], " 中");
+    }
+
+    #[test]
+    fn grapheme_cluster_kept_in_one_line() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_gc_{}.txt", process::id()));
+        fs::write(&fpath, "abcde\u{1F468}\u{200D}\u{1F469}\n".as_bytes()).unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = MIN_TEXT_WIDTH;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        con.def.grapheme = true;
+        let res = res.and_then(|_| show_page(&mut con));
+        let g_page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(page[0], " abcde\u{1F468}\u{200D}");
+        assert_eq!(page[1], " \u{1F469}");
+        assert_eq!(g_page[0], " abcde\u{1F468}\u{200D}\u{1F469}");
+    }
+
+    #[test]
+    fn display_size_from_terminal() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_fit_{}.txt", process::id()));
+        fs::write(&fpath, b"").unwrap();
+        let mut con = ctx_of(&fpath);
+        fs::remove_file(&fpath).unwrap();
+        fit_size(&mut con, 100, 30);
+        assert_eq!(con.def_dlwidth, 100);
+        assert_eq!(con.def_dheight, 30 - TERM_RESERVED_ROWS);
+        con.def.status = true;
+        fit_size(&mut con, 1, 1);
+        assert_eq!(con.def_dlwidth, MIN_DISPLAY_LINE_WIDTH);
+        assert_eq!(con.def_dheight, MIN_DISPLAY_HEIGHT);
+        fit_size(&mut con, 100, 30);
+        assert_eq!(con.def_dheight, 30 - TERM_RESERVED_ROWS - 1);
+        con.def.dlwidth_pinned = true;
+        fit_size(&mut con, 50, 30);
+        assert_eq!(con.def_dlwidth, 100);
+    }
+
+    #[test]
+    fn status_line_after_page() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_status_{}.txt", process::id()));
+        fs::write(&fpath, b"a\nb\nc\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.text_file_path_str = format!("{}/x.txt", "d".repeat(60));
+        con.def_dlwidth = 60;
+        con.def_dheight = 2;
+        con.def.status = true;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        let fnm = fpath.file_name().unwrap().to_string_lossy();
+        assert_eq!(page.len(), 3);
+        assert_eq!(page[2], format!("{} 0% 0-3/6 UTF-8", fnm));
+        con.def_dlwidth = 20;
+        assert_eq!(status_line(&con), format!("{} 0% 0-3/6 UTF-8", fnm)[..20]);
+    }
+
+    #[test]
+    fn line_numbers_in_gutter() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_nu_{}.txt", process::id()));
+        fs::write(&fpath, b"a\nbbbbbbbbbbbb\nc\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = 12;
+        con.def.nu = true;
+        con.def.bookmark = 2;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(page[..3], [" 2  bbbbbbbb", "    bbbb", " 3 $c"]);
+        assert_eq!(con.def.line_anchors.get(&2), Some(&1));
+    }
+
+    #[test]
+    fn offsets_in_gutter() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_off_{}.txt", process::id()));
+        fs::write(&fpath, b"a\nbbbbbbbbbbbb\nc\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def_dlwidth = 12;
+        con.def.offsets = Some(OffsetRadix::Dec);
+        con.def.bookmark = 2;
+        let res = show_page(&mut con);
+        let page = take_dlines(&con);
+        con.def.offsets = Some(OffsetRadix::Hex);
+        let res = res.and_then(|_| show_page(&mut con));
+        let hex_page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(page[..3], [" 2  bbbbbbbb", "10  bbbb", "15 $c"]);
+        assert_eq!(hex_page[..2], ["00000002  bbbbbbb", "00000009  bbbbb"]);
+    }
+
+    #[test]
+    fn search_hits_highlighted() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_hl_{}.txt", process::id()));
+        fs::write(&fpath, b"xx foo yy foo\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.hl = true;
+        con.def.iline = "/foo".to_owned();
+        let res = search_bytes(&mut con);
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(con.def.bookmark, 3);
+        assert_eq!(page[0], " \x1b[7mfoo\x1b[27m yy \x1b[7mfoo\x1b[27m");
+    }
+
+    #[test]
+    fn backward_search() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_rev_{}.txt", process::id()));
+        fs::write(&fpath, b"foo a foo b foo\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.bookmark = 12;
+        con.def.iline = "?foo".to_owned();
+        let mut hits = vec![];
+        let mut res = Ok(());
+        for _ in 0..3 {
+            res = res.and_then(|_| search_bytes_rev(&mut con));
+            hits.push(con.def.bookmark);
+        }
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(hits, vec![6, 0, 0]);
+        assert_eq!(con.def.search_pat.blob, b"foo");
+    }
+
+    #[test]
+    fn search_wraps_around() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_wrap_{}.txt", process::id()));
+        fs::write(&fpath, b"foo a foo b\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut hits = vec![];
+        let mut walk = || -> CustRes<()> {
+            for wrapscan in [false, true] {
+                con.def.wrapscan = wrapscan;
+                con.def.bookmark = 7;
+                con.def.iline = "/foo".to_owned();
+                search_bytes(&mut con)?;
+                hits.push(con.def.bookmark);
+                con.def.bookmark = 0;
+                con.def.iline = "?foo".to_owned();
+                search_bytes_rev(&mut con)?;
+                hits.push(con.def.bookmark);
+            }
+            Ok(())
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        assert_eq!(hits, vec![7, 0, 0, 6]);
+    }
+
+    #[test]
+    fn hits_in_order_across_parallel_chunks() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        //note the second hit straddles the edge of third chunk
+        let mut blob = vec![b'a'; SEARCH_CHUNK_LEN * 5];
+        let offs = [
+            SEARCH_CHUNK_LEN / 2,
+            SEARCH_CHUNK_LEN * 3 - 3,
+            SEARCH_CHUNK_LEN * 4,
+        ];
+        for off in offs {
+            blob[off..off + 6].copy_from_slice(b"needle");
+        }
+        let fpath = env::temp_dir().join(format!("gmtc_par_{}.txt", process::id()));
+        fs::write(&fpath, &blob).unwrap();
+        let mut con = ctx_of(&fpath);
+        let pat = pat_of(b"needle");
+        let len = blob.len() as u64;
+        let mut prog = SearchProgress::new(len);
+        let mut walk = || -> CustRes<_> {
+            Ok((
+                scan(&mut con, &pat, 0, len, false, &mut prog)?,
+                scan(&mut con, &pat, 0, len, true, &mut prog)?,
+                scan_hits(&mut con, &pat, 0, len, false, 10, &mut prog)?,
+                scan_hits(&mut con, &pat, 0, len, true, 2, &mut prog)?,
+            ))
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        let (first, last, hits, rev_hits) = res.unwrap();
+        let offs = offs.map(|off| (off as u64, 6));
+        assert_eq!(first, Some(offs[0]));
+        assert_eq!(last, Some(offs[2]));
+        assert_eq!(hits, offs);
+        assert_eq!(rev_hits, [offs[2], offs[1]]);
+    }
+
+    #[test]
+    fn json_navigation() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_json_{}.txt", process::id()));
+        let cont = br#"{"a": "x\"]", "b": [1, {"c": "}"}], "d": null}"#;
+        fs::write(&fpath, cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        let pos = |ptn: &str| {
+            cont.windows(ptn.len())
+                .position(|win| win == ptn.as_bytes())
+                .unwrap() as u64
+        };
+        let mut stops = vec![];
+        let mut walk = || -> CustRes<()> {
+            for (from, cmd) in [
+                (pos(r#""a""#), "jn"),
+                (pos(r#""b""#), "jn"),
+                (pos("["), "jm"),
+                (pos("],"), "jm"),
+                (pos(r#""c""#), "ju"),
+                (pos("{\"c"), "ju"),
+                (pos(r#""x"#), "jm"),
+            ] {
+                con.def.bookmark = from;
+                con.def.iline = cmd.to_owned();
+                cmd_json(&mut con)?;
+                stops.push(con.def.bookmark);
+            }
+            Ok(())
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        let (_, tok) = json_state_at(&mut con, pos("]"), &mut SearchProgress::new(0)).unwrap();
+        assert!(tok.in_str);
+        assert_eq!(
+            stops,
+            [
+                pos(r#""b""#),
+                pos(r#""d""#),
+                pos("],"),
+                pos("["),
+                pos("{\"c"),
+                pos("["),
+                pos(r#""x"#)
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_pretty_markup_with_gt() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_xml_{}.txt", process::id()));
+        let cont = br#"<a x="1>2"><!-- <b> --><b>t</b><c/></a>"#;
+        fs::write(&fpath, cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut prog = SearchProgress::new(cont.len() as u64);
+        let mut walk = || -> CustRes<_> {
+            let whole = xml_pretty(&mut con, 0, 0, XmlTok::default(), 1000, &mut prog)?;
+            let (depth, tok) = xml_state_at(&mut con, 26, &mut prog)?;
+            let inner = xml_pretty(&mut con, 26, depth, tok, 1000, &mut prog)?;
+            Ok((whole, depth, inner))
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        let (whole, depth, inner) = res.unwrap();
+        assert_eq!(
+            whole,
+            b"<a x=\"1>2\">\n  <!-- <b> -->\n  <b>t</b>\n  <c/>\n</a>"
+        );
+        assert_eq!(depth, 2);
+        assert_eq!(inner, b"    t\n  </b>\n  <c/>\n</a>");
+    }
+
+    #[test]
+    fn csv_fields_with_quotes() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_csv_{}.txt", process::id()));
+        //note a quote inside an unquoted field is a plain char
+        let cont = b"a,\"b,\"\"c\"\"\",d\n\"e\nf\",g\nx\"y,z\n";
+        fs::write(&fpath, cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.csv = Some(b',');
+        let mut fields = vec![];
+        let res = csv_walk(
+            &mut con,
+            0,
+            &mut SearchProgress::new(0),
+            |start, end, ender| {
+                let field =
+                    String::from_utf8_lossy(&cont[start as usize..end as usize]).into_owned();
+                fields.push((field, ender));
+                true
+            },
+        );
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|(field, ender)| (field.as_str(), *ender))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("a", b','),
+                ("\"b,\"\"c\"\"\"", b','),
+                ("d", b'\n'),
+                ("\"e\nf\"", b','),
+                ("g", b'\n'),
+                ("x\"y", b','),
+                ("z", b'\n')
+            ]
+        );
+    }
+
+    #[test]
+    fn log_rules_color_spans() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_log_{}.txt", process::id()));
+        let dpath = env::temp_dir().join(format!("gmtc_log_{}", process::id()));
+        fs::create_dir_all(&dpath).unwrap();
+        fs::write(
+            dpath.join(LOG_RULES_FILENM),
+            "# comment\n31 ERROR\n1;33 ERR|y\nbad\n",
+        )
+        .unwrap();
+        fs::write(&fpath, b"x ERROR y\n").unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.app_support_dir = dpath.clone();
+        con.def.loghl = true;
+        let res = load_log_rules(&mut con).and_then(|_| show_page(&mut con));
+        let page = take_dlines(&con);
+        fs::remove_file(&fpath).unwrap();
+        fs::remove_dir_all(&dpath).unwrap();
+        res.unwrap();
+        assert_eq!(con.def.log_rules.len(), 2);
+        assert_eq!(page[0], " x \x1b[31mERROR\x1b[0m \x1b[1;33my\x1b[0m");
+    }
+
+    #[test]
+    fn jump_to_timestamp() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let ts = |txt| parse_ts(txt).unwrap().to_string();
+        assert_eq!(ts("2024-03-01 10:20:30,5"), "2024-03-01 10:20:30.500");
+        assert_eq!(ts("2024-03-01T10:20"), "2024-03-01 10:20:00");
+        assert_eq!(ts("2024-03-01"), "2024-03-01 00:00:00");
+        assert!(parse_ts("2024-13-01").is_none());
+        let fpath = env::temp_dir().join(format!("gmtc_ts_{}.txt", process::id()));
+        let mut cont = String::new();
+        for hour in 0..24 {
+            cont += &format!(
+                "[2024-03-01 {:02}:00:00] line {}\n  continued\n",
+                hour, hour
+            );
+        }
+        fs::write(&fpath, &cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        let mut stops = vec![];
+        let mut res = Ok(());
+        for target in [
+            "2024-03-01 05:00",
+            "2024-03-01 05:30",
+            "2024-02-01",
+            "2024-04-01",
+        ] {
+            con.def.bookmark = 7;
+            con.def.iline = format!("t {}", target);
+            res = res.and_then(|_| cmd_t(&mut con));
+            stops.push(con.def.bookmark);
+        }
+        fs::remove_file(&fpath).unwrap();
+        res.unwrap();
+        let line_at = |hour: usize| cont.find(&format!("{:02}:00:00]", hour)).unwrap() as u64 - 12;
+        assert_eq!(stops, [line_at(5), line_at(6), 0, 7]);
+    }
+
+    #[test]
+    fn binary_search_for_key() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_bs_{}.txt", process::id()));
+        let cont = "1,apple\r\n2,banana\r\n3,cherry\r\n4,date\r\n";
+        fs::write(&fpath, cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        con.def.bs_delim = b',';
+        let mut stops = vec![];
+        let mut walk = || -> CustRes<_> {
+            let key = bs_key_after(&mut con, 3)?;
+            for (field, key) in [(0, "2,b"), (0, "25"), (2, "cherry"), (2, "blue")] {
+                con.def.bs_field = field;
+                con.def.bookmark = 7;
+                con.def.iline = format!("bs {}", key);
+                cmd_bs(&mut con)?;
+                stops.push(con.def.bookmark);
+            }
+            Ok(key)
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        let key = res.unwrap();
+        assert_eq!(key, Some((9, b"2,banana".to_vec())));
+        let line_at = |txt| cont.find(txt).unwrap() as u64;
+        assert_eq!(stops, [line_at("2,b"), 7, line_at("3,c"), 7]);
+    }
+
+    #[test]
+    fn bookmark_relocated_after_edit() {
+        let _guard = CTX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let fpath = env::temp_dir().join(format!("gmtc_bm_{}.txt", process::id()));
+        let cont: String = (0..200).map(|idx| format!("line {}\n", idx)).collect();
+        let off = cont.find("line 120").unwrap() as u64;
+        fs::write(&fpath, &cont).unwrap();
+        let mut con = ctx_of(&fpath);
+        let reopen = |con: &mut Ctx, new_cont: &str| -> CustRes<()> {
+            fs::write(&fpath, new_cont)?;
+            con.def.fsfile = Some(fs::File::open(&fpath)?);
+            con.def.fsmd = Some(fs::metadata(&fpath)?);
+            Ok(())
+        };
+        let mut walk = || -> CustRes<_> {
+            let (ctx_before, ctx) = bookmark_ctx(&mut con, off, cont.len() as u64)?;
+            let hash = short_hash(&mut con, &ctx);
+            let txt = format!(
+                "{} {} 7 {} {} {}",
+                off,
+                cont.len(),
+                ctx_before,
+                ctx.len(),
+                hash
+            );
+            let (parsed_off, rec) = parse_bookmark(&txt)?;
+            let rec = rec.unwrap();
+            reopen(&mut con, &format!("inserted\n{}", cont))?;
+            let moved = relocate_bookmark(&mut con, off, &rec)?;
+            reopen(&mut con, &cont.replace("line 12", "LINE 12"))?;
+            let gone = relocate_bookmark(&mut con, off, &rec)?;
+            Ok((parsed_off, rec.mtime, moved, gone, parse_bookmark("42")?))
+        };
+        let res = walk();
+        fs::remove_file(&fpath).unwrap();
+        let (parsed_off, mtime, moved, gone, legacy) = res.unwrap();
+        assert_eq!(parsed_off, off);
+        assert_eq!(mtime, 7);
+        assert_eq!(moved, Some(off + 9));
+        assert_eq!(gone, None);
+        assert_eq!(legacy.0, 42);
+        assert!(legacy.1.is_none());
+    }
+}